@@ -0,0 +1,11 @@
+-- Your SQL goes here
+CREATE TABLE manual_keywords (
+  id SERIAL PRIMARY KEY,
+  word VARCHAR NOT NULL,
+  weight INTEGER NOT NULL DEFAULT 2,
+  document VARCHAR
+           REFERENCES documents(name)
+           ON UPDATE CASCADE
+           ON DELETE CASCADE
+           NOT NULL
+)
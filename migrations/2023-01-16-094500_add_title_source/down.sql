@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE documents DROP COLUMN title_source;
+
+DROP TYPE TitleSource;
@@ -0,0 +1,12 @@
+-- Your SQL goes here
+CREATE TYPE TitleSource AS ENUM (
+  'metadata',
+  'open_graph',
+  'heading',
+  'first_line',
+  'name',
+  'manual'
+);
+
+ALTER TABLE documents
+  ADD COLUMN title_source TitleSource NOT NULL DEFAULT 'metadata';
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE keywords DROP COLUMN field;
+
+DROP TYPE KeywordField;
@@ -0,0 +1,13 @@
+-- Your SQL goes here
+CREATE TYPE KeywordField AS ENUM (
+  'title',
+  'description',
+  'heading',
+  'emphasis',
+  'body',
+  'keywords',
+  'boilerplate'
+);
+
+ALTER TABLE keywords
+  ADD COLUMN field KeywordField NOT NULL DEFAULT 'body';
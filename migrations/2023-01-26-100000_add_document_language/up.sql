@@ -0,0 +1,2 @@
+-- Your SQL goes here
+ALTER TABLE documents ADD COLUMN language VARCHAR;
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE manual_keywords DROP COLUMN form;
+ALTER TABLE keywords DROP COLUMN form;
@@ -0,0 +1,3 @@
+-- Your SQL goes here
+ALTER TABLE keywords ADD COLUMN form VARCHAR;
+ALTER TABLE manual_keywords ADD COLUMN form VARCHAR;
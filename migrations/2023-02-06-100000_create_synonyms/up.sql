@@ -0,0 +1,7 @@
+-- Your SQL goes here
+CREATE TABLE synonyms (
+  id SERIAL PRIMARY KEY,
+  language VARCHAR NOT NULL,
+  words TEXT[] NOT NULL,
+  expansions TEXT[] NOT NULL DEFAULT '{}'
+);
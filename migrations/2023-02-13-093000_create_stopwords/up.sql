@@ -0,0 +1,7 @@
+-- Your SQL goes here
+CREATE TABLE stopwords (
+  language VARCHAR NOT NULL,
+  word VARCHAR NOT NULL,
+  removed BOOLEAN NOT NULL DEFAULT FALSE,
+  PRIMARY KEY (language, word)
+);
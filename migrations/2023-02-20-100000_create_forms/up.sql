@@ -0,0 +1,11 @@
+-- Your SQL goes here
+CREATE TABLE forms (
+  document VARCHAR
+           REFERENCES documents(name)
+           ON UPDATE CASCADE
+           ON DELETE CASCADE
+           NOT NULL,
+  form VARCHAR NOT NULL,
+  occurrences INTEGER NOT NULL,
+  PRIMARY KEY (document, form)
+);
@@ -0,0 +1,354 @@
+use std::collections::HashSet;
+use std::sync::{PoisonError, RwLock};
+
+use rayon::prelude::*;
+use rust_stemmers::Stemmer;
+
+use crate::glaff::Glaff;
+use crate::kwparser::{self, Term};
+use crate::normalizer::Normalizer;
+use crate::tokenizer::{Token, Tokenizer};
+
+/// Length in bytes above which texts are analysed in parallel chunks
+const CHUNK_LENGTH: usize = 64 * 1024;
+
+/// Analysis of text into terms
+///
+/// The same analyzer must be used to index documents and to search
+/// them, so that the terms of queries match the terms of documents.
+/// Text is normalized and split into tokens, then each word is
+/// replaced by its lemma or stem unless it is a stop word.
+#[derive(Default)]
+pub struct Analyzer {
+    /// Words to ignore, folded by the normalizer. They may be replaced
+    /// while the server runs, see [`Analyzer::set_stopwords`].
+    ///
+    /// [`Analyzer::set_stopwords`]: ./struct.Analyzer.html#method.set_stopwords
+    stopwords: RwLock<HashSet<String>>,
+    /// Lemmas of inflected words, such as the GLÀFF for French,
+    /// indexed by folded words
+    pub glaff: Option<Glaff>,
+    /// Stemmer used for words without a lemma
+    pub stemmer: Option<Stemmer>,
+    /// Normalization of words, see [`Analyzer::with_normalizer`]
+    ///
+    /// [`Analyzer::with_normalizer`]: ./struct.Analyzer.html#method.with_normalizer
+    pub normalizer: Normalizer,
+    /// Tokenizer splitting text into words
+    pub tokenizer: Tokenizer,
+}
+
+impl Analyzer {
+    /// Ignore `words`
+    #[must_use]
+    pub fn with_stopwords(self, words: &[String]) -> Self {
+        self.set_stopwords(words);
+        self
+    }
+
+    /// Replace words by their lemma found in `glaff`
+    ///
+    /// The forms of `glaff` must be folded like the words of the
+    /// analyzer, see [`Glaff::is_folded_by`].
+    ///
+    /// [`Glaff::is_folded_by`]: ../glaff/struct.Glaff.html#method.is_folded_by
+    #[must_use]
+    pub fn with_glaff(mut self, glaff: Option<Glaff>) -> Self {
+        self.glaff = glaff;
+        self
+    }
+
+    /// Normalize words with `normalizer`
+    ///
+    /// Stop words are folded by `normalizer` so they can be compared
+    /// to folded words. The GLÀFF must have been compiled with the
+    /// same normalization, see [`Glaff::is_folded_by`].
+    ///
+    /// [`Glaff::is_folded_by`]: ../glaff/struct.Glaff.html#method.is_folded_by
+    #[must_use]
+    pub fn with_normalizer(mut self, normalizer: Normalizer) -> Self {
+        if normalizer.folds() {
+            let stopwords = self
+                .stopwords
+                .get_mut()
+                .unwrap_or_else(PoisonError::into_inner);
+            *stopwords =
+                stopwords.iter().map(|word| normalizer.fold(word)).collect();
+        }
+        self.normalizer = normalizer;
+        self
+    }
+
+    /// Current stop words, in no particular order
+    #[must_use]
+    pub fn stopwords(&self) -> Vec<String> {
+        self.stopwords
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .cloned()
+            .collect()
+    }
+
+    /// Replace the stop words with `words`
+    ///
+    /// Words are folded by the normalizer first, then replace all
+    /// stop words at once, so that texts being analysed use either the
+    /// previous or the new stop words. Empty words are left out.
+    pub fn set_stopwords(&self, words: &[String]) {
+        let words = words
+            .iter()
+            .map(|word| self.normalizer.fold(word.trim()))
+            .filter(|word| !word.is_empty())
+            .collect();
+        *self
+            .stopwords
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = words;
+    }
+
+    /// Get the term under which a word is indexed and searched
+    ///
+    /// `word` is normalized and folded, then replaced by its lemma
+    /// (see [`kwparser::get_lemma`]), which is folded in turn.
+    ///
+    /// [`kwparser::get_lemma`]: ../kwparser/fn.get_lemma.html
+    #[must_use]
+    pub fn term(&self, word: &str) -> String {
+        let word = self.normalizer.normalize_text(word).to_lowercase();
+        self.normalizer.fold(&kwparser::get_lemma(
+            self.normalizer.fold(&word),
+            None,
+            &self.glaff,
+            self.stemmer.as_ref(),
+        ))
+    }
+
+    /// Turn a token into a keyword
+    ///
+    /// Words are turned into their term (see [`Analyzer::term`]),
+    /// unless they or their lemma are stop words. The previous word,
+    /// stop words included, helps choosing among several lemmas, and
+    /// is replaced by the token’s word. Other tokens, such as
+    /// numbers, URLs, or email addresses, are only normalized,
+    /// lowercased, and folded.
+    ///
+    /// [`Analyzer::term`]: ./struct.Analyzer.html#method.term
+    fn analyze(
+        &self,
+        stopwords: &HashSet<String>,
+        token: &Token,
+        previous: &mut String,
+    ) -> Option<Term> {
+        let form = self.normalizer.normalize_text(token.text).to_lowercase();
+        let mut word = self.normalizer.fold(&form);
+        let before = std::mem::replace(previous, word.clone());
+        if token.kind.is_linguistic() {
+            if stopwords.contains(&word) {
+                return None;
+            }
+            word = self.normalizer.fold(&kwparser::get_lemma(
+                word,
+                Some(before.as_str()).filter(|before| !before.is_empty()),
+                &self.glaff,
+                self.stemmer.as_ref(),
+            ));
+            if word.is_empty() || stopwords.contains(&word) {
+                return None;
+            }
+        }
+        Some(Term {
+            word,
+            form,
+            offset: token.offset,
+            position: token.position,
+        })
+    }
+
+    /// Iterate over the forms of the words of a text, in the order
+    /// they appear in
+    ///
+    /// Unlike keywords, stop words and words shorter than the minimum
+    /// length are kept, and hyphenated compounds are not split into
+    /// their parts. Forms are normalized and lowercased, but neither
+    /// folded nor lemmatized, so they are the words to correct the
+    /// spelling of, see [`Dictionary::correct_phrase`].
+    ///
+    /// [`Dictionary::correct_phrase`]: ../spelling/struct.Dictionary.html#method.correct_phrase
+    pub fn forms<'a>(
+        &'a self,
+        text: &'a str,
+    ) -> impl Iterator<Item = String> + 'a {
+        let tokenizer = Tokenizer {
+            min_length: 1,
+            ..self.tokenizer
+        };
+        let mut end = 0;
+        tokenizer.tokenize(text).filter_map(move |token| {
+            // Parts of a compound follow it and lie within it
+            if token.offset < end {
+                return None;
+            }
+            end = token.offset + token.text.len();
+            let form = self.normalizer.normalize_text(token.text);
+            Some(form.to_lowercase())
+        })
+    }
+
+    /// Iterate over the keywords of a text, in the order they appear
+    /// in
+    ///
+    /// Keywords are read lazily from the tokens of `text`, see
+    /// [`Tokenizer::tokenize`]. Their offsets refer to `text`. Stop
+    /// words cannot be replaced until the iterator is dropped.
+    ///
+    /// [`Tokenizer::tokenize`]: ../tokenizer/struct.Tokenizer.html#method.tokenize
+    pub fn terms<'a>(
+        &'a self,
+        text: &'a str,
+    ) -> impl Iterator<Item = Term> + 'a {
+        let stopwords = self
+            .stopwords
+            .read()
+            .unwrap_or_else(PoisonError::into_inner);
+        let mut previous = String::new();
+        self.tokenizer.tokenize(text).filter_map(move |token| {
+            self.analyze(&stopwords, &token, &mut previous)
+        })
+    }
+
+    /// Folded last word of `text`, which is the previous word of the
+    /// token following `text`, see [`Analyzer::analyze`]
+    ///
+    /// Whitespace-separated chunks of text are tokenized on their own,
+    /// so only the last chunks of `text` are read.
+    ///
+    /// [`Analyzer::analyze`]: ./struct.Analyzer.html#method.analyze
+    fn last_word(&self, text: &str) -> String {
+        text.split_whitespace()
+            .rev()
+            .find_map(|chunk| self.tokenizer.tokenize(chunk).last())
+            .map(|token| {
+                let form = self.normalizer.normalize_text(token.text);
+                self.normalizer.fold(&form.to_lowercase())
+            })
+            .unwrap_or_default()
+    }
+
+    /// Extract the keywords of a text, in the order they appear in
+    ///
+    /// Long texts are split at whitespace into chunks analysed in
+    /// parallel, see [`Analyzer::terms`]. The first word of each chunk
+    /// is analysed along with the last word of the previous one, so
+    /// the keywords are the same as if the text was analysed at once.
+    ///
+    /// [`Analyzer::terms`]: ./struct.Analyzer.html#method.terms
+    #[must_use]
+    pub fn keywords(&self, text: &str) -> Vec<Term> {
+        if text.len() <= CHUNK_LENGTH {
+            return self.terms(text).collect();
+        }
+        let stopwords = self
+            .stopwords
+            .read()
+            .unwrap_or_else(PoisonError::into_inner);
+        let chunks = chunks(text, CHUNK_LENGTH)
+            .into_par_iter()
+            .map(|(start, chunk)| {
+                let mut tokens = self.tokenizer.tokenize(chunk);
+                let mut previous = self.last_word(&text[..start]);
+                let terms = tokens
+                    .by_ref()
+                    .filter_map(|token| {
+                        self.analyze(&stopwords, &token, &mut previous)
+                    })
+                    .collect::<Vec<Term>>();
+                (start, terms, tokens.positions())
+            })
+            .collect::<Vec<_>>();
+        let mut keywords = Vec::new();
+        let mut position = 0;
+        for (start, terms, positions) in chunks {
+            keywords.extend(terms.into_iter().map(|term| Term {
+                offset: term.offset + start,
+                position: term.position + position,
+                ..term
+            }));
+            position += positions;
+        }
+        keywords
+    }
+}
+
+/// Split `text` at whitespace into chunks of about `length` bytes
+///
+/// Return each chunk along with its offset in `text`.
+fn chunks(text: &str, length: usize) -> Vec<(usize, &str)> {
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    while start < text.len() {
+        let mut end = (start + length).min(text.len());
+        while !text.is_char_boundary(end) {
+            end += 1;
+        }
+        end = text[end..]
+            .find(char::is_whitespace)
+            .map_or(text.len(), |index| end + index);
+        chunks.push((start, &text[start..end]));
+        start = end;
+    }
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunks_split_at_whitespace() {
+        let text = "un deux trois quatre";
+        let chunks = chunks(text, 5);
+        assert_eq!(chunks, [(0, "un deux"), (7, " trois"), (13, " quatre")]);
+        assert_eq!(
+            chunks.iter().map(|(_, chunk)| *chunk).collect::<String>(),
+            text
+        );
+    }
+
+    #[test]
+    fn chunks_do_not_split_characters() {
+        let text = "ééé ééé";
+        for (start, chunk) in chunks(text, 1) {
+            assert_eq!(&text[start..start + chunk.len()], chunk);
+        }
+    }
+
+    #[test]
+    fn last_word_skips_short_words() {
+        let analyzer = Analyzer::default();
+        assert_eq!(analyzer.last_word("Le CHAT a"), "chat");
+        assert_eq!(analyzer.last_word("de c’ "), "");
+    }
+
+    #[test]
+    fn forms_keep_short_words_and_compounds() {
+        let analyzer = Analyzer::default()
+            .with_stopwords(&["de".to_string(), "le".to_string()]);
+        let forms = analyzer
+            .forms("Le ﬁlm de l’arc-en-ciel, 2023 !")
+            .collect::<Vec<_>>();
+        assert_eq!(forms, ["le", "film", "de", "l", "arc-en-ciel", "2023"]);
+    }
+
+    #[test]
+    fn chunked_keywords_match_whole_text() {
+        let analyzer = Analyzer::default()
+            .with_stopwords(&["les".to_string(), "des".to_string()]);
+        let text = "Les chats mangent des souris grises. ".repeat(4000);
+        assert!(text.len() > CHUNK_LENGTH);
+        let keywords = analyzer.keywords(&text);
+        assert_eq!(keywords, analyzer.terms(&text).collect::<Vec<_>>());
+        assert_eq!(keywords.len(), 4 * 4000);
+        assert_eq!(keywords.last().unwrap().position, 6 * 4000 - 1);
+    }
+}
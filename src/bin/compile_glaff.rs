@@ -1,10 +1,12 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs::File;
-use std::io::Write;
+use std::io::{BufWriter, Write};
 use std::path::PathBuf;
 
 use color_eyre::eyre::Result;
-use indexer::kwparser::Glaff;
+use fst::MapBuilder;
+use indexer::glaff::{DIACRITICS, LIGATURES, MAGIC};
+use indexer::normalizer::Normalizer;
 use rocket::serde::Deserialize;
 use structopt::StructOpt;
 use tracing::info;
@@ -16,70 +18,252 @@ struct Opt {
     #[structopt(short = "o", long, parse(from_os_str))]
     output: PathBuf,
 
+    /// Normalization of the indexer, as given to its --normalization
+    /// option, with which inflected forms are folded
+    #[structopt(long, default_value = "nfkc")]
+    normalization: Normalizer,
+
     /// Path to the GLÀFF
     #[structopt(name = "FILE", parse(from_os_str))]
     file: PathBuf,
 }
 
+/// Inflected form of the GLÀFF
+#[derive(Debug, Clone, PartialEq)]
+struct Inflection {
+    form: String,
+    lemma: String,
+    /// Morphosyntactic description of the form, as a GRACE tag such
+    /// as `Ncms` for a masculine singular common noun
+    pos: String,
+    /// Frequency of the form, in occurrences per million words
+    frequency: f32,
+}
+
 #[derive(Deserialize)]
 #[serde(crate = "rocket::serde")]
 struct GlaffRecord<'a> {
     word: &'a str,
-    _grace: &'a str,
+    grace: &'a str,
     lemme: &'a str,
     _ipa: &'a str,
     _sampa: &'a str,
     _frq1: f32,
-    _frq2: f32,
+    frq2: f32,
     _frq3: f32,
     _frq4: f32,
     _frq5: f32,
-    _frq6: f32,
+    frq6: f32,
     _frq7: f32,
     _frq8: f32,
     _frq9: f32,
-    _frq10: f32,
+    frq10: f32,
     _frq11: f32,
     _frq12: f32,
 }
 
+impl GlaffRecord<'_> {
+    /// Frequency of the form, in occurrences per million words
+    ///
+    /// The GLÀFF gives, for each of its three corpora, the absolute
+    /// and relative frequencies of the form then of its lemma. The
+    /// relative frequencies of the form are averaged.
+    fn frequency(&self) -> f32 {
+        (self.frq2 + self.frq6 + self.frq10) / 3.0
+    }
+}
+
 /// Parse the GLÀFF
 ///
-/// Results in a `HashMap` containing on the first hand pretty much
-/// all words in the French language, and on the other hand its
-/// canonical form.
+/// Results in a list containing pretty much all words in the French
+/// language, along with their canonical form, their part of speech,
+/// and their frequency.
 ///
-/// If `path` is `None`, return nothing (useful when not dealing with
-/// French text)
+/// # Errors
 ///
-/// # Panics
-///
-/// The program may panic if it fails to correctly parse the GLÀFF. If
-/// it does, verify if your version is not corrupted.
-pub fn parse_glaff(file: PathBuf) -> Result<Glaff> {
+/// If the GLÀFF cannot be read or parsed, return the error to the
+/// caller. If it does, verify if your version is not corrupted.
+fn parse_glaff(file: PathBuf) -> Result<Vec<Inflection>> {
     let mut reader = csv::ReaderBuilder::new()
         .delimiter(b'|')
         .has_headers(false)
-        .from_path(file)
-        .unwrap();
-    let mut lemme: Glaff = HashMap::new();
+        .from_path(file)?;
+    let mut lemme = Vec::new();
     for record in reader.records() {
         let row = record?;
         let row: GlaffRecord = row.deserialize(None)?;
-        lemme.insert(row.word.to_string(), row.lemme.to_string());
+        lemme.push(Inflection {
+            form: row.word.to_string(),
+            lemma: row.lemme.to_string(),
+            pos: row.grace.to_string(),
+            frequency: row.frequency(),
+        });
     }
     Ok(lemme)
 }
 
+/// Compile the GLÀFF from its `inflections` and write it to `writer`
+///
+/// Inflected forms are folded by `normalizer`, and forms which only
+/// differ by their diacritics or ligatures are merged. All lemmas of
+/// a form are kept, from the most to the least frequent. When a form
+/// appears several times with the same lemma and GRACE tag, its
+/// highest frequency is kept. See [`Glaff`] for the format of the
+/// compiled GLÀFF.
+///
+/// # Errors
+///
+/// If the FST cannot be built or `writer` fails, return the error to
+/// the caller.
+///
+/// [`Glaff`]: ../indexer/glaff/struct.Glaff.html
+fn compile<I, W>(
+    inflections: I,
+    normalizer: Normalizer,
+    mut writer: W,
+) -> Result<()>
+where
+    I: IntoIterator<Item = Inflection>,
+    W: Write,
+{
+    let mut forms: BTreeMap<String, Vec<Inflection>> = BTreeMap::new();
+    for inflection in inflections {
+        let lemmas =
+            forms.entry(normalizer.fold(&inflection.form)).or_default();
+        match lemmas.iter_mut().find(|other| {
+            other.lemma == inflection.lemma && other.pos == inflection.pos
+        }) {
+            Some(other) => {
+                other.frequency = other.frequency.max(inflection.frequency);
+            }
+            None => lemmas.push(inflection),
+        }
+    }
+    let mut offsets: HashMap<String, u32> = HashMap::new();
+    let mut strings: Vec<u8> = Vec::new();
+    let mut string = |string: &str| -> Result<u32> {
+        if let Some(&offset) = offsets.get(string) {
+            return Ok(offset);
+        }
+        let offset = u32::try_from(strings.len())?;
+        strings.extend_from_slice(string.as_bytes());
+        strings.push(0);
+        offsets.insert(string.to_string(), offset);
+        Ok(offset)
+    };
+    let mut candidates: Vec<u8> = Vec::new();
+    let mut builder = MapBuilder::memory();
+    for (form, mut lemmas) in forms {
+        lemmas.sort_by(|a, b| b.frequency.total_cmp(&a.frequency));
+        lemmas.truncate(usize::from(u16::MAX));
+        builder.insert(&form, candidates.len() as u64)?;
+        candidates
+            .extend_from_slice(&u16::try_from(lemmas.len())?.to_le_bytes());
+        for inflection in lemmas {
+            let lemma = string(&inflection.lemma)?;
+            let pos = string(&inflection.pos)?;
+            candidates.extend_from_slice(&lemma.to_le_bytes());
+            candidates.extend_from_slice(&pos.to_le_bytes());
+            candidates.extend_from_slice(&inflection.frequency.to_le_bytes());
+        }
+    }
+    let forms = builder.into_inner()?;
+    let mut flags = 0;
+    if normalizer.diacritics {
+        flags |= DIACRITICS;
+    }
+    if normalizer.ligatures {
+        flags |= LIGATURES;
+    }
+    writer.write_all(MAGIC)?;
+    writer.write_all(&(forms.len() as u64).to_le_bytes())?;
+    writer.write_all(&(candidates.len() as u64).to_le_bytes())?;
+    writer.write_all(&flags.to_le_bytes())?;
+    writer.write_all(&forms)?;
+    writer.write_all(&candidates)?;
+    writer.write_all(&strings)?;
+    writer.flush()?;
+    Ok(())
+}
+
 fn main() -> Result<()> {
     indexer::setup_logging();
     let opt = Opt::from_args();
     info!("Reading the GLÀFF");
     let glaff = parse_glaff(opt.file)?;
-    info!("Converting GLÀFF to binary");
-    let glaff_bin = bincode::serialize(&glaff)?;
-    info!("Writing to {}", opt.output.display());
-    let mut file = File::create(opt.output)?;
-    file.write_all(glaff_bin.as_ref())?;
+    info!("Compiling GLÀFF to {}", opt.output.display());
+    let file = BufWriter::new(File::create(opt.output)?);
+    compile(glaff, opt.normalization, file)?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indexer::glaff::Glaff;
+
+    fn inflection(
+        form: &str,
+        lemma: &str,
+        pos: &str,
+        frequency: f32,
+    ) -> Inflection {
+        Inflection {
+            form: form.to_string(),
+            lemma: lemma.to_string(),
+            pos: pos.to_string(),
+            frequency,
+        }
+    }
+
+    fn open(inflections: Vec<Inflection>, normalization: &str) -> Glaff {
+        let normalizer = normalization.parse::<Normalizer>().unwrap();
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        compile(inflections, normalizer, file.as_file_mut()).unwrap();
+        Glaff::open(file.path()).unwrap()
+    }
+
+    #[test]
+    fn lemmas_are_sorted_by_frequency() {
+        let glaff = open(
+            vec![
+                inflection("est", "est", "Ncms", 10.0),
+                inflection("est", "être", "Vmip3s", 5000.0),
+                inflection("est", "est", "Ncms", 20.0),
+                inflection("le", "le", "Da-ms-d", 9000.0),
+                inflection("il", "il", "Pp3msn", 8000.0),
+            ],
+            "nfkc",
+        );
+        let lemmas = glaff.lemmas("est");
+        let lemmas = lemmas
+            .iter()
+            .map(|lemma| (lemma.lemma, lemma.pos, lemma.frequency))
+            .collect::<Vec<_>>();
+        assert_eq!(lemmas, [("être", "Vmip3s", 5000.0), ("est", "Ncms", 20.0)]);
+        assert_eq!(glaff.get("est", Some("il")), Some("être"));
+        assert_eq!(glaff.get("est", Some("le")), Some("est"));
+        assert_eq!(glaff.get("inconnu", None), None);
+    }
+
+    #[test]
+    fn folded_forms_share_their_lemmas() {
+        let inflections = vec![
+            inflection("élève", "élève", "Ncfs", 30.0),
+            inflection("élevé", "élevé", "Afpms", 40.0),
+            inflection("œuvre", "œuvre", "Ncfs", 50.0),
+        ];
+        let glaff = open(inflections.clone(), "nfkc,diacritics");
+        assert!(glaff.is_folded_by("diacritics".parse().unwrap()));
+        assert!(!glaff.is_folded_by(Normalizer::default()));
+        let lemmas = glaff.lemmas("eleve");
+        let lemmas = lemmas.iter().map(|lemma| lemma.lemma).collect::<Vec<_>>();
+        assert_eq!(lemmas, ["élevé", "élève"]);
+        assert!(glaff.lemmas("élève").is_empty());
+        assert_eq!(glaff.get("œuvre", None), Some("œuvre"));
+
+        let glaff = open(inflections, "ligatures");
+        assert_eq!(glaff.get("oeuvre", None), Some("œuvre"));
+        assert_eq!(glaff.get("élève", None), Some("élève"));
+    }
+}
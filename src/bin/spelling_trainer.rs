@@ -1,55 +1,341 @@
-use color_eyre::eyre::Result;
-use indexer::kwparser::{get_keywords_from_text, get_stopwords};
+use color_eyre::eyre::{eyre, Result};
+use indexer::analyzer::Analyzer;
+use indexer::fileparser::{self, charset};
+use indexer::language::normalize_tag;
 use indexer::spelling::Dictionary;
-use std::fs::File;
+use indexer::tokenizer::Tokenizer;
+use rayon::prelude::*;
+use std::fs::{self, File};
 use std::io::Write;
-use std::{collections::HashMap, fs::read_to_string, path::PathBuf};
+use std::path::Path;
+use std::{collections::HashMap, path::PathBuf};
 use structopt::StructOpt;
+use tracing::{info, warn};
+
+/// Marker of the license of Project Gutenberg books
+const GUTENBERG: &str = "PROJECT GUTENBERG";
 
 #[derive(StructOpt, Debug)]
 #[structopt(name = "spelltrainer")]
 struct Opt {
-    /// List of stop words to ignore
-    #[structopt(short = "s", long, parse(from_os_str))]
-    stop_words: PathBuf,
-
     /// Output path of the dictionary
     #[structopt(short = "o", long, parse(from_os_str))]
     output: PathBuf,
 
-    /// Corpus files to process
-    #[structopt(name = "FILE", parse(from_os_str))]
-    files: Vec<PathBuf>,
+    /// ISO 639-1 code of the language of the corpus, which decides how
+    /// words are split, such as elisions in French
+    #[structopt(long, default_value = "fr")]
+    language: String,
+
+    /// Corpus files or directories to process, walked recursively
+    #[structopt(name = "PATH", parse(from_os_str))]
+    paths: Vec<PathBuf>,
+}
+
+/// Words of the corpus and pairs of consecutive words, along with
+/// their number of occurrences
+#[derive(Default)]
+struct Counts {
+    keywords: HashMap<String, usize>,
+    bigrams: HashMap<String, usize>,
+}
+
+impl Counts {
+    /// Add the occurrences of `other`
+    fn merge(mut self, other: Self) -> Self {
+        for (word, occurrences) in other.keywords {
+            *self.keywords.entry(word).or_default() += occurrences;
+        }
+        for (pair, occurrences) in other.bigrams {
+            *self.bigrams.entry(pair).or_default() += occurrences;
+        }
+        self
+    }
+}
+
+/// List the files under `paths`, walking directories recursively
+///
+/// Symbolic links to directories found while walking are skipped, as
+/// they may form cycles.
+///
+/// # Errors
+///
+/// If a directory cannot be read, return the error to the caller.
+fn corpus_files(paths: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut pending = paths.to_vec();
+    while let Some(path) = pending.pop() {
+        if path.is_dir() {
+            for entry in fs::read_dir(&path)? {
+                let entry = entry?;
+                if entry.file_type()?.is_symlink() && entry.path().is_dir() {
+                    warn!(
+                        "Skipping linked directory {}",
+                        entry.path().display()
+                    );
+                    continue;
+                }
+                pending.push(entry.path());
+            }
+        } else {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Find a Project Gutenberg marker preceded by one of `prefixes` in
+/// `text`, starting at `from`
+///
+/// Return the offsets of the start and of the end of the marker.
+fn gutenberg_marker(
+    text: &str,
+    from: usize,
+    prefixes: &[&str],
+) -> Option<(usize, usize)> {
+    text[from..]
+        .match_indices(GUTENBERG)
+        .find_map(|(index, _)| {
+            let index = from + index;
+            prefixes
+                .iter()
+                .find(|prefix| text[..index].ends_with(*prefix))
+                .map(|prefix| (index - prefix.len(), index + GUTENBERG.len()))
+        })
+}
+
+/// Strip the header and the footer of a Project Gutenberg book
+///
+/// The header ends with the first blank line following the “START OF
+/// THE PROJECT GUTENBERG” or “START OF THIS PROJECT GUTENBERG” line,
+/// and the footer starts with the matching “END OF” line. Texts
+/// without these markers are left as they are.
+fn strip_gutenberg(text: &str) -> &str {
+    let header = ["START OF ", "START OF THE ", "START OF THIS "];
+    let Some((_, end)) = gutenberg_marker(text, 0, &header) else {
+        return text;
+    };
+    let mut start = match text[end..].find('\n') {
+        Some(index) => end + index + 1,
+        None => text.len(),
+    };
+    for line in text[start..].split_inclusive('\n') {
+        if line.trim().is_empty() {
+            break;
+        }
+        start += line.len();
+    }
+    let text = &text[start..];
+    let footer = ["END OF ", "END OF THE ", "END OF THIS "];
+    match gutenberg_marker(text, 0, &footer) {
+        Some((end, _)) => match text[..end].rfind('\n') {
+            Some(index) => &text[..=index],
+            None => "",
+        },
+        None => text,
+    }
+}
+
+/// Turn an XML corpus into plain text
+///
+/// Words annotated with a `x:wf` element are replaced by their `word`
+/// attribute, other tags are removed, and apostrophes and quotes are
+/// unescaped. Punctuation is then followed by a single space and
+/// apostrophes by none, runs of spaces are collapsed, and spaced out
+/// ellipses are joined again.
+fn clean_xml(xml: &str) -> String {
+    let mut text = String::with_capacity(xml.len());
+    let mut rest = xml;
+    while let Some(start) = rest.find('<') {
+        let Some(length) = rest[start..].find('>') else {
+            break;
+        };
+        text.push_str(&rest[..start]);
+        let tag = &rest[start + 1..start + length];
+        if let Some((word, _)) = tag
+            .strip_prefix("x:wf word='")
+            .and_then(|tag| tag.split_once('\''))
+        {
+            text.push_str(word);
+            text.push(' ');
+        }
+        rest = &rest[start + length + 1..];
+    }
+    text.push_str(rest);
+    let text = text.replace("&apos;", "'").replace("&quot;", "\"");
+    let mut cleaned = String::with_capacity(text.len());
+    for character in text.chars() {
+        match character {
+            ',' | '.' => {
+                cleaned.truncate(cleaned.trim_end_matches(' ').len());
+                cleaned.push(character);
+                cleaned.push(' ');
+            }
+            ' ' if cleaned.ends_with([' ', '\'']) => {}
+            character => cleaned.push(character),
+        }
+    }
+    cleaned.replace(". . .", "...")
 }
 
-fn train(files: &[PathBuf], stop_words: &[String]) -> HashMap<String, usize> {
-    let mut keywords: HashMap<String, usize> = HashMap::new();
-    files
-        .iter()
-        .map(read_to_string)
-        .flat_map(|s| get_keywords_from_text(&s.unwrap(), stop_words, &None))
-        .for_each(|word| {
-            keywords
-                .entry(word)
-                .and_modify(|value| *value += 1)
-                .or_insert(1);
-        });
-    keywords
+/// Read the text of a corpus file
+///
+/// XML files are cleaned up with [`clean_xml`], other files are
+/// parsed according to their format, see [`fileparser::parse`]. The
+/// header and footer of Project Gutenberg books are stripped.
+///
+/// # Errors
+///
+/// If the file cannot be read or parsed, return the error to the
+/// caller.
+fn read_corpus_file(path: &Path) -> Result<String> {
+    let doc = fs::read(path)?;
+    let is_xml = path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("xml"));
+    let text = if is_xml {
+        clean_xml(&charset::decode(&doc, None))
+    } else {
+        fileparser::parse(&doc, None, None, &path.to_string_lossy())
+            .map_err(|error| eyre!("{error:?}"))?
+            .body
+    };
+    Ok(strip_gutenberg(&text).to_string())
+}
+
+/// Count the words of a text and the pairs of consecutive words
+///
+/// Stop words and short words are counted as well, see
+/// [`Analyzer::forms`], so that queries are corrected in the context
+/// of all their words.
+fn count(analyzer: &Analyzer, text: &str) -> Counts {
+    let forms = analyzer.forms(text).collect::<Vec<String>>();
+    let mut counts = Counts::default();
+    for form in &forms {
+        *counts.keywords.entry(form.clone()).or_default() += 1;
+    }
+    for pair in forms.windows(2) {
+        *counts
+            .bigrams
+            .entry(format!("{} {}", pair[0], pair[1]))
+            .or_default() += 1;
+    }
+    counts
+}
+
+/// Count the words of the corpus and the pairs of consecutive words
+///
+/// Files are read and counted in parallel. Files that cannot be read
+/// are skipped with a warning. Pairs of words seen only once are left
+/// out. Words are split according to `language`.
+fn train(files: &[PathBuf], language: &str) -> Counts {
+    let mut analyzer = Analyzer::default();
+    analyzer.tokenizer = Tokenizer::for_language(language, 3);
+    let mut counts = files
+        .par_iter()
+        .filter_map(|path| match read_corpus_file(path) {
+            Ok(text) => Some(count(&analyzer, &text)),
+            Err(error) => {
+                warn!("Skipping {}: {error}", path.display());
+                None
+            }
+        })
+        .reduce(Counts::default, Counts::merge);
+    counts.bigrams.retain(|_, occurrences| *occurrences > 1);
+    counts
 }
 
 fn main() -> Result<()> {
     indexer::setup_logging();
     color_eyre::install()?;
     let opt = Opt::from_args();
-    let stop_words = get_stopwords(opt.stop_words);
-    let keywords = train(&opt.files, &stop_words);
-    let dictionary = Dictionary {
-        n: keywords.iter().fold(0, |acc, (_, value)| acc + value),
-        words: keywords,
-        edits: Vec::new(),
-    };
+    let language = normalize_tag(&opt.language)
+        .ok_or_else(|| eyre!("Invalid language tag {:?}", opt.language))?;
+    let files = corpus_files(&opt.paths)?;
+    info!("== Training on {} files", files.len());
+    let counts = train(&files, &language);
+    let dictionary = Dictionary::new(counts.keywords, counts.bigrams);
     let dictionary_bin = bincode::serialize(&dictionary)?;
     let mut file = File::create(opt.output)?;
     file.write_all(dictionary_bin.as_ref())?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BOOK: &str = "The Project Gutenberg eBook of Candide\n\
+        License: see below\n\
+        *** START OF THE PROJECT GUTENBERG EBOOK CANDIDE ***\n\
+        Produced by volunteers\n\
+        \n\
+        Il y avait en Westphalie\n\
+        un jeune garçon.\n\
+        *** END OF THE PROJECT GUTENBERG EBOOK CANDIDE ***\n\
+        License text\n";
+
+    #[test]
+    fn gutenberg_header_and_footer_are_stripped() {
+        assert_eq!(
+            strip_gutenberg(BOOK),
+            "\nIl y avait en Westphalie\nun jeune garçon.\n"
+        );
+    }
+
+    #[test]
+    fn gutenberg_header_variants_are_recognized() {
+        for prefix in ["START OF", "START OF THE", "START OF THIS"] {
+            let text = format!(
+                "Header\n*** {prefix} PROJECT GUTENBERG EBOOK ***\n\nBody\n"
+            );
+            assert_eq!(strip_gutenberg(&text), "\nBody\n", "{prefix}");
+        }
+    }
+
+    #[test]
+    fn gutenberg_text_without_footer_is_kept_to_the_end() {
+        let text = "*** START OF THIS PROJECT GUTENBERG EBOOK ***\n\
+            Credits\n\
+            \n\
+            Body\n";
+        assert_eq!(strip_gutenberg(text), "\nBody\n");
+    }
+
+    #[test]
+    fn text_without_gutenberg_markers_is_left_untouched() {
+        let text = "A text about the PROJECT GUTENBERG\nBody\n";
+        assert_eq!(strip_gutenberg(text), text);
+    }
+
+    #[test]
+    fn annotated_words_replace_xml_elements() {
+        let xml = "<s><x:wf word='Il' lemma='il'/>\
+            <x:wf word='mange' lemma='manger'/></s>";
+        assert_eq!(clean_xml(xml), "Il mange ");
+    }
+
+    #[test]
+    fn xml_punctuation_and_apostrophes_are_spaced() {
+        let xml = "<p>l&apos; arbre , dit -il .Puis   &quot;non&quot;</p>";
+        assert_eq!(clean_xml(xml), "l'arbre, dit -il. Puis \"non\"");
+    }
+
+    #[test]
+    fn xml_ellipses_are_joined() {
+        assert_eq!(clean_xml("<p>Et puis ... rien.</p>"), "Et puis... rien. ");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn linked_directories_are_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let books = dir.path().join("books");
+        fs::create_dir(&books).unwrap();
+        fs::write(books.join("candide.txt"), "Candide").unwrap();
+        std::os::unix::fs::symlink(dir.path(), books.join("loop")).unwrap();
+        let files = corpus_files(&[dir.path().to_path_buf()]).unwrap();
+        assert_eq!(files, [books.join("candide.txt")]);
+    }
+}
@@ -12,23 +12,45 @@ use std::collections::HashMap;
 
 pub mod models;
 pub mod schema;
+pub mod weights;
 
-use models::{Document, Keyword};
-use schema::{documents, keywords};
+use models::{
+    Document, DocumentChanges, Field, Keyword, SynonymChanges, SynonymGroup,
+};
+use schema::{
+    documents, forms, keywords, manual_keywords, stopwords, synonyms,
+};
+use weights::FieldWeights;
 
 use crate::fileparser::ParsedDocument;
 
 pub type DatabaseResult<T> = Result<T, diesel::result::Error>;
 
+// Group forms by the language of their document, see `vocabulary`
+diesel::allow_columns_to_appear_in_same_group_by_clause!(
+    documents::language,
+    forms::form,
+);
+
+/// Weight of a manual keyword when none is specified
+pub const DEFAULT_MANUAL_WEIGHT: i32 = 2;
+
+/// Number of forms inserted at once, see `insert_keywords`
+const FORMS_PER_INSERT: usize = 10_000;
+
 /// List of migrations the database may have to perform when indexer
 /// is launching
 pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!();
 
 /// Run the list of migrations held by `MIGRATIONS`.
 ///
+/// All pending migrations are run at once, so the database is up to
+/// date before the stop words and synonyms are loaded on startup.
+///
 /// # Errors
 ///
-/// If any error is encountered while running a migration, return
+/// If any error is encountered while running a migration, return it
+/// as a database error to the function calling `run_migrations`.
 pub fn run_migrations(
     connection: &mut impl MigrationHarness<diesel::pg::Pg>,
 ) -> DatabaseResult<()> {
@@ -37,7 +59,7 @@ pub fn run_migrations(
         Ok(migrate) => {
             if migrate {
                 connection
-                    .run_next_migration(MIGRATIONS)
+                    .run_pending_migrations(MIGRATIONS)
                     .map(|_| ())
                     .map_err(|e| {
                         Error::DatabaseError(
@@ -70,8 +92,11 @@ pub fn get_connection_pool() -> Pool<ConnectionManager<PgConnection>> {
 
 /// Insert a keyword in the database
 ///
-/// Insert the keyword `word` associated with the document `doc` in
-/// the database. This function assumes the document already exists.
+/// Insert the keyword `word` found `occurrences` times in the field
+/// `field` of the document `doc` in the database. If `word` is new to
+/// this field of the document, `form` is stored along with it for
+/// display when it differs from `word`. This function assumes the
+/// document already exists.
 ///
 /// # Errors
 ///
@@ -83,8 +108,10 @@ pub fn get_connection_pool() -> Pool<ConnectionManager<PgConnection>> {
 pub fn insert_word(
     conn: &mut PgConnection,
     word: &str,
+    form: &str,
     doc: &str,
-    weight: Option<i32>,
+    field: Field,
+    occurrences: i32,
 ) -> DatabaseResult<()> {
     use keywords::dsl;
     // Verify if the document exists before inserting keywords
@@ -99,17 +126,20 @@ pub fn insert_word(
     if let Ok(val) = keywords::dsl::keywords
         .filter(dsl::document.eq(doc))
         .filter(dsl::word.eq(word))
+        .filter(dsl::field.eq(field))
         .first::<Keyword>(conn)
     {
         diesel::update(dsl::keywords.find(val.id))
-            .set(dsl::occurrences.eq(val.occurrences + weight.unwrap_or(1)))
+            .set(dsl::occurrences.eq(val.occurrences + occurrences))
             .execute(conn)?;
     } else {
         diesel::insert_into(keywords::dsl::keywords)
             .values((
                 dsl::word.eq(word),
                 dsl::document.eq(doc),
-                dsl::occurrences.eq(weight.unwrap_or(1)),
+                dsl::field.eq(field),
+                dsl::occurrences.eq(occurrences),
+                dsl::form.eq((form != word).then_some(form)),
             ))
             .execute(conn)?;
     }
@@ -119,6 +149,11 @@ pub fn insert_word(
 use crate::server::RankedKeyword;
 /// List keywords associated with a document
 ///
+/// The rank of each keyword is the sum of its occurrences in each
+/// field of the document, weighted by `weights`, and of its weight as
+/// a manual keyword. Keywords are listed under the form they were
+/// found in rather than their normalized form.
+///
 /// # Errors
 ///
 /// Errors may be returned by Diesel, forward them to the function
@@ -126,31 +161,46 @@ use crate::server::RankedKeyword;
 pub fn doc_list_keywords(
     conn: &mut PgConnection,
     document: &str,
+    weights: &FieldWeights,
 ) -> DatabaseResult<Vec<RankedKeyword>> {
     use keywords::dsl;
-    let mut keywords: Vec<RankedKeyword> = dsl::keywords
+    let mut ranks: HashMap<String, (String, i32)> = HashMap::new();
+    dsl::keywords
         .filter(dsl::document.eq(document))
-        .select((dsl::word, dsl::occurrences))
-        .load::<(String, i32)>(conn)?
-        .iter()
-        .map(|k| RankedKeyword {
-            keyword: k.0.clone(),
-            rank: k.1,
+        .select((dsl::word, dsl::form, dsl::field, dsl::occurrences))
+        .load::<(String, Option<String>, Field, i32)>(conn)?
+        .into_iter()
+        .map(|(word, form, field, occurrences)| {
+            (word, form, occurrences * weights.get(field))
         })
+        .chain(list_manual_keywords(conn, document)?)
+        .for_each(|(word, form, rank)| {
+            let entry = ranks
+                .entry(word.clone())
+                .or_insert_with(|| (form.clone().unwrap_or(word), 0));
+            entry.1 += rank;
+        });
+    let mut keywords: Vec<RankedKeyword> = ranks
+        .into_values()
+        .filter(|(_, rank)| *rank > 0)
+        .map(|(keyword, rank)| RankedKeyword { keyword, rank })
         .collect();
     keywords.sort_by_key(|k| k.rank);
     keywords.reverse();
     Ok(keywords)
 }
 
-use crate::server::RankedDoc;
-
 use self::models::DocType;
 
 /// Search a document by keywords
 ///
-/// Return the documents matching at least one of the `words`, ordered
-/// in descending order by the amount of hits per word.
+/// Return the documents matching at least one of the `words` along
+/// with their amount of hits, ordered in descending order by the
+/// amount of hits per word. Each word is given along with its weight
+/// in percent, so that words expanded from the query, such as
+/// synonyms, weigh less than the query’s own words. Hits are weighted
+/// by `weights` depending on the field they are found in as well, and
+/// documents without any hit once weighted are left out.
 ///
 /// # Errors
 ///
@@ -158,48 +208,81 @@ use self::models::DocType;
 /// calling `keywords_search`.
 pub fn keywords_search(
     conn: &mut PgConnection,
-    words: &[String],
-) -> DatabaseResult<Vec<RankedDoc>> {
+    words: &[(String, i32)],
+    weights: &FieldWeights,
+) -> DatabaseResult<Vec<(Document, i32)>> {
     let mut docs: HashMap<Document, i32> = HashMap::new();
-    for word in words {
+    for (word, percent) in words {
         let list = keywords::table
-            .left_join(
-                documents::table.on(keywords::document
-                    .eq(documents::name)
-                    .and(keywords::word.eq(word))),
-            )
-            .load::<(Keyword, Option<Document>)>(conn)?
-            .iter()
-            .filter_map(|item| {
-                item.1.as_ref().map(|doc| (doc.clone(), item.0.occurrences))
+            .inner_join(documents::table)
+            .filter(keywords::word.eq(word))
+            .select((
+                documents::all_columns,
+                keywords::field,
+                keywords::occurrences,
+            ))
+            .load::<(Document, Field, i32)>(conn)?
+            .into_iter()
+            .map(|(doc, field, occurrences)| {
+                (doc, occurrences * weights.get(field))
             })
             .collect::<Vec<(Document, i32)>>();
+        let manual = manual_keywords::table
+            .inner_join(documents::table)
+            .filter(manual_keywords::word.eq(word))
+            .select((documents::all_columns, manual_keywords::weight))
+            .load::<(Document, i32)>(conn)?;
         debug!("Documents for query {words:?}: {list:?}");
-        for item in list {
-            docs.entry(item.0)
-                .and_modify(|occ| *occ += item.1)
-                .or_insert(item.1);
+        for (doc, hits) in list.into_iter().chain(manual) {
+            *docs.entry(doc).or_insert(0) += hits * percent;
         }
     }
+    // Round hits up so that documents only matching expanded words
+    // are not left out
     let mut docs: Vec<(Document, i32)> = docs
-        .iter()
-        .map(|(doc, occ)| (doc.clone(), occ.to_owned()))
+        .into_iter()
+        .filter(|(_, hits)| *hits > 0)
+        .map(|(doc, hits)| (doc, (hits + 99) / 100))
         .collect();
     docs.sort_by_key(|k| k.1);
     docs.reverse();
-    Ok(docs
-        .iter()
-        .map(|k| RankedDoc {
-            hits: Some(k.1.to_owned()),
-            ..k.0.clone().into()
+    Ok(docs)
+}
+
+/// Count the occurrences of the forms of words in all documents
+///
+/// Return triples of the language of documents if known, the form of
+/// words as found in these documents, stop words included, and their
+/// number of occurrences in all fields of these documents.
+///
+/// # Errors
+///
+/// If any error is returned by the database, forward it to the
+/// function calling `vocabulary`
+pub fn vocabulary(
+    conn: &mut PgConnection,
+) -> DatabaseResult<Vec<(Option<String>, String, i64)>> {
+    let rows = forms::table
+        .inner_join(documents::table)
+        .group_by((documents::language, forms::form))
+        .select((
+            documents::language,
+            forms::form,
+            diesel::dsl::sum(forms::occurrences),
+        ))
+        .load::<(Option<String>, String, Option<i64>)>(conn)?;
+    Ok(rows
+        .into_iter()
+        .map(|(language, form, occurrences)| {
+            (language, form, occurrences.unwrap_or_default())
         })
-        .collect::<Vec<RankedDoc>>())
+        .collect())
 }
 
 /// Add a document to the indexer
 ///
 /// Add a document’s description to the database as well as its
-/// keywords
+/// keywords and the field in which they are found.
 ///
 /// # Errors
 ///
@@ -214,15 +297,172 @@ pub fn add_document(
     diesel::insert_into(dsl::documents)
         .values(document.clone())
         .execute(conn)?;
-    for keyword in &content.keywords {
-        insert_word(conn, keyword, &document.name, Some(2))?;
+    insert_keywords(conn, &document.name, content)
+}
+
+/// Insert the keywords extracted from a document
+///
+/// Keywords are counted in each field of `content` before being
+/// inserted as keywords of `document`, see [`insert_word`]. The forms
+/// of the words of `document` are inserted as well.
+///
+/// # Errors
+///
+/// Errors may be returned by Diesel, forward them to the function
+/// calling `insert_keywords`.
+///
+/// [`insert_word`]: ./fn.insert_word.html
+fn insert_keywords(
+    conn: &mut PgConnection,
+    document: &str,
+    content: &ParsedDocument,
+) -> DatabaseResult<()> {
+    let mut occurrences: HashMap<(Field, &str), (&str, i32)> = HashMap::new();
+    for (field, keywords) in &content.fields {
+        for keyword in keywords {
+            occurrences
+                .entry((*field, &keyword.word))
+                .or_insert((&keyword.form, 0))
+                .1 += 1;
+        }
+    }
+    for ((field, word), (form, occurrences)) in occurrences {
+        insert_word(conn, word, form, document, field, occurrences)?;
     }
-    for keyword in &content.content {
-        insert_word(conn, keyword, &document.name, None)?;
+    let rows = content
+        .forms
+        .iter()
+        .map(|(form, occurrences)| {
+            (
+                forms::document.eq(document),
+                forms::form.eq(form),
+                forms::occurrences.eq(occurrences),
+            )
+        })
+        .collect::<Vec<_>>();
+    // Stay below the limit of 65535 parameters of a PostgreSQL query
+    for rows in rows.chunks(FORMS_PER_INSERT) {
+        diesel::insert_into(forms::table)
+            .values(rows)
+            .execute(conn)?;
     }
     Ok(())
 }
 
+/// Replace the keywords extracted from a document
+///
+/// The keywords and forms of `document` are replaced by the ones of
+/// `content`, for instance once it is parsed again. Its metadata and
+/// manual keywords are left untouched.
+///
+/// # Errors
+///
+/// Errors may be returned by Diesel, forward them to the function
+/// calling `replace_keywords`.
+pub fn replace_keywords(
+    conn: &mut PgConnection,
+    document: &str,
+    content: &ParsedDocument,
+) -> DatabaseResult<()> {
+    use keywords::dsl;
+    conn.transaction(|conn| {
+        diesel::delete(dsl::keywords.filter(dsl::document.eq(document)))
+            .execute(conn)?;
+        diesel::delete(forms::table.filter(forms::document.eq(document)))
+            .execute(conn)?;
+        insert_keywords(conn, document, content)
+    })
+}
+
+/// List documents holding any of the keywords `words`
+///
+/// # Errors
+///
+/// Errors may be returned by Diesel, forward them to the function
+/// calling `documents_with_keywords`.
+pub fn documents_with_keywords(
+    conn: &mut PgConnection,
+    words: &[String],
+) -> DatabaseResult<Vec<Document>> {
+    keywords::table
+        .inner_join(documents::table)
+        .filter(keywords::word.eq_any(words))
+        .select(documents::all_columns)
+        .distinct()
+        .load(conn)
+}
+
+/// List manual keywords associated with a document
+///
+/// Return each manual keyword of `document` along with the form it
+/// was given in, if it differs, and its weight.
+///
+/// # Errors
+///
+/// Errors may be returned by Diesel, forward them to the function
+/// calling `list_manual_keywords`.
+pub fn list_manual_keywords(
+    conn: &mut PgConnection,
+    document: &str,
+) -> DatabaseResult<Vec<(String, Option<String>, i32)>> {
+    use manual_keywords::dsl;
+    dsl::manual_keywords
+        .filter(dsl::document.eq(document))
+        .select((dsl::word, dsl::form, dsl::weight))
+        .load::<(String, Option<String>, i32)>(conn)
+}
+
+/// Update a document’s metadata
+///
+/// Apply `changes` to the document `id`. If `keywords` is set, the
+/// manual keywords of the document are replaced by these keywords,
+/// each given as its normalized form, the form given by the user, and
+/// its weight. Keywords extracted from the document’s content are
+/// left untouched. Return the updated document.
+///
+/// # Errors
+///
+/// If the document does not exist, a `NotFound` error is returned.
+/// Other errors may be returned by Diesel, forward them to the
+/// function calling `update_document`.
+pub fn update_document(
+    conn: &mut PgConnection,
+    id: &str,
+    changes: &DocumentChanges,
+    keywords: Option<&[(String, String, i32)]>,
+) -> DatabaseResult<Document> {
+    conn.transaction(|conn| {
+        let document = documents::dsl::documents.find(id);
+        // Fail early with `NotFound` if the document does not exist
+        document.first::<Document>(conn)?;
+        if !changes.is_empty() {
+            diesel::update(document)
+                .set(changes.clone().with_title_source())
+                .execute(conn)?;
+        }
+        if let Some(keywords) = keywords {
+            use manual_keywords::dsl;
+            diesel::delete(dsl::manual_keywords.filter(dsl::document.eq(id)))
+                .execute(conn)?;
+            let rows = keywords
+                .iter()
+                .map(|(word, form, weight)| {
+                    (
+                        dsl::word.eq(word),
+                        dsl::weight.eq(weight),
+                        dsl::document.eq(id),
+                        dsl::form.eq((form != word).then_some(form)),
+                    )
+                })
+                .collect::<Vec<_>>();
+            diesel::insert_into(dsl::manual_keywords)
+                .values(rows)
+                .execute(conn)?;
+        }
+        document.first::<Document>(conn)
+    })
+}
+
 /// List documents indexed in the database
 ///
 /// # Errors
@@ -267,12 +507,15 @@ pub fn get_document(conn: &mut PgConnection, id: &str) -> Option<Document> {
     }
 }
 
-/// Retrieve the S3 filename of a document
+/// Retrieve the filename of a stored document
 ///
 /// If a document’s primary key matches the argument `id` and that
-/// document is a document stored on the S3 remote storage, return its
-/// filename.
-pub fn get_s3_filename(conn: &mut PgConnection, id: &str) -> Option<String> {
+/// document is a document kept in the server’s storage backend,
+/// return its filename.
+pub fn get_stored_filename(
+    conn: &mut PgConnection,
+    id: &str,
+) -> Option<String> {
     if let Some(document) = get_document(conn, id) {
         if document.doctype == DocType::Offline {
             Some(document.name)
@@ -283,3 +526,174 @@ pub fn get_s3_filename(conn: &mut PgConnection, id: &str) -> Option<String> {
         None
     }
 }
+
+/// List synonym groups
+///
+/// # Errors
+///
+/// If any error is returned by the database, forward it to the
+/// function calling `list_synonyms`
+pub fn list_synonyms(
+    conn: &mut PgConnection,
+) -> DatabaseResult<Vec<SynonymGroup>> {
+    use synonyms::dsl;
+    dsl::synonyms.order(dsl::id).load(conn)
+}
+
+/// Create a synonym group
+///
+/// `group` must hold at least the language and the words of the
+/// group. Return the created group.
+///
+/// # Errors
+///
+/// If any error is returned by the database, forward it to the
+/// function calling `add_synonyms`
+pub fn add_synonyms(
+    conn: &mut PgConnection,
+    group: &SynonymChanges,
+) -> DatabaseResult<SynonymGroup> {
+    use synonyms::dsl;
+    diesel::insert_into(dsl::synonyms)
+        .values(group.clone())
+        .get_result(conn)
+}
+
+/// Update the synonym group `id`
+///
+/// Return the updated group.
+///
+/// # Errors
+///
+/// If the group does not exist, a `NotFound` error is returned. Other
+/// errors may be returned by Diesel, forward them to the function
+/// calling `update_synonyms`.
+pub fn update_synonyms(
+    conn: &mut PgConnection,
+    id: i32,
+    changes: &SynonymChanges,
+) -> DatabaseResult<SynonymGroup> {
+    use synonyms::dsl;
+    if changes.language.is_none()
+        && changes.words.is_none()
+        && changes.expansions.is_none()
+    {
+        return dsl::synonyms.find(id).first(conn);
+    }
+    diesel::update(dsl::synonyms.find(id))
+        .set(changes.clone())
+        .get_result(conn)
+}
+
+/// Delete the synonym group `id`
+///
+/// # Errors
+///
+/// If the group does not exist, a `NotFound` error is returned. Other
+/// errors may be returned by Diesel, forward them to the function
+/// calling `delete_synonyms`.
+pub fn delete_synonyms(conn: &mut PgConnection, id: i32) -> DatabaseResult<()> {
+    use synonyms::dsl;
+    match diesel::delete(dsl::synonyms.find(id)).execute(conn)? {
+        0 => Err(diesel::NotFound),
+        _ => Ok(()),
+    }
+}
+
+/// List stop words of all languages
+///
+/// Return pairs of languages and stop words, leaving out removed stop
+/// words.
+///
+/// # Errors
+///
+/// If any error is returned by the database, forward it to the
+/// function calling `list_stopwords`
+pub fn list_stopwords(
+    conn: &mut PgConnection,
+) -> DatabaseResult<Vec<(String, String)>> {
+    use stopwords::dsl;
+    dsl::stopwords
+        .filter(dsl::removed.eq(false))
+        .select((dsl::language, dsl::word))
+        .order((dsl::language, dsl::word))
+        .load(conn)
+}
+
+/// Import stop words of `language`, such as the ones read from a file
+///
+/// Stop words already known, including removed ones, are left
+/// untouched, so that removing a stop word through the API outlives
+/// its file.
+///
+/// # Errors
+///
+/// If any error is returned by the database, forward it to the
+/// function calling `import_stopwords`
+pub fn import_stopwords(
+    conn: &mut PgConnection,
+    language: &str,
+    words: &[String],
+) -> DatabaseResult<()> {
+    use stopwords::dsl;
+    let rows = words
+        .iter()
+        .filter(|word| !word.is_empty())
+        .map(|word| (dsl::language.eq(language), dsl::word.eq(word)))
+        .collect::<Vec<_>>();
+    diesel::insert_into(dsl::stopwords)
+        .values(rows)
+        .on_conflict_do_nothing()
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Add or remove stop words of `language`
+///
+/// `words` are added as stop words, or removed if `removed` is true.
+/// Return the words which were actually added or removed, leaving
+/// out the ones which already were.
+///
+/// # Errors
+///
+/// If any error is returned by the database, forward it to the
+/// function calling `update_stopwords`
+pub fn update_stopwords(
+    conn: &mut PgConnection,
+    language: &str,
+    words: &[String],
+    removed: bool,
+) -> DatabaseResult<Vec<String>> {
+    use diesel::upsert::excluded;
+    use stopwords::dsl;
+    conn.transaction(|conn| {
+        let active = dsl::stopwords
+            .filter(dsl::language.eq(language))
+            .filter(dsl::word.eq_any(words))
+            .filter(dsl::removed.eq(false))
+            .select(dsl::word)
+            .load::<String>(conn)?;
+        let changed = words
+            .iter()
+            .filter(|word| active.contains(word) == removed)
+            .cloned()
+            .collect::<Vec<String>>();
+        let rows = changed
+            .iter()
+            .map(|word| {
+                (
+                    dsl::language.eq(language),
+                    dsl::word.eq(word),
+                    dsl::removed.eq(removed),
+                )
+            })
+            .collect::<Vec<_>>();
+        diesel::insert_into(dsl::stopwords)
+            .values(rows)
+            .on_conflict((dsl::language, dsl::word))
+            .do_update()
+            .set(dsl::removed.eq(excluded(dsl::removed)))
+            .execute(conn)?;
+        Ok(changed)
+    })
+}
@@ -1,4 +1,4 @@
-use crate::db::schema::{documents, keywords};
+use crate::db::schema::{documents, keywords, manual_keywords, synonyms};
 use diesel::prelude::*;
 use rocket::serde::{Deserialize, Serialize};
 
@@ -20,6 +20,74 @@ pub enum DocType {
     Offline,
 }
 
+/// Part of a document in which a keyword is found
+///
+/// Keywords are weighted differently depending on the field they are
+/// found in, see [`FieldWeights`].
+///
+/// [`FieldWeights`]: ../weights/struct.FieldWeights.html
+#[derive(
+    Debug,
+    Serialize,
+    Deserialize,
+    Clone,
+    PartialEq,
+    Eq,
+    Copy,
+    diesel_derive_enum::DbEnum,
+    Hash,
+)]
+#[DieselTypePath = "crate::db::schema::sql_types::Keywordfield"]
+#[serde(crate = "rocket::serde")]
+pub enum Field {
+    Title,
+    Description,
+    /// Headings of level 1 to 3
+    Heading,
+    /// Emphasised text, such as bold or italic text
+    Emphasis,
+    /// Main text of the document, anchor text included, as links are
+    /// not weighted on their own
+    Body,
+    /// Keywords declared in the document’s metadata
+    Keywords,
+    /// Navigation menus, footers, and other text surrounding the body
+    Boilerplate,
+}
+
+/// Where the title of a document comes from
+///
+/// Documents often lack a proper title in their metadata, in which
+/// case their title is found through other means, from the most to
+/// the least reliable.
+#[derive(
+    Debug,
+    Serialize,
+    Deserialize,
+    Clone,
+    PartialEq,
+    Eq,
+    Copy,
+    diesel_derive_enum::DbEnum,
+    Hash,
+)]
+#[DieselTypePath = "crate::db::schema::sql_types::Titlesource"]
+#[serde(crate = "rocket::serde")]
+pub enum TitleSource {
+    /// Title declared in the document’s metadata
+    Metadata,
+    /// Title declared in the `og:title` Open Graph property
+    OpenGraph,
+    /// First heading of the document
+    Heading,
+    /// First line of the document’s text
+    FirstLine,
+    /// Name of the document, that is its filename or URL
+    Name,
+    /// Title set manually through the API
+    Manual,
+}
+
 #[derive(
     Debug,
     Queryable,
@@ -37,6 +105,41 @@ pub struct Document {
     pub title: String,
     pub doctype: DocType,
     pub description: String,
+    pub language: Option<String>,
+    pub title_source: TitleSource,
+}
+
+/// Changes to apply to a document’s metadata
+///
+/// Fields set to `None` are left untouched. `title_source` cannot be
+/// set by users, see [`DocumentChanges::with_title_source`].
+#[derive(Debug, AsChangeset, Default, Deserialize, Clone)]
+#[diesel(table_name = documents)]
+#[serde(crate = "rocket::serde")]
+pub struct DocumentChanges {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub language: Option<String>,
+    #[serde(skip)]
+    pub title_source: Option<TitleSource>,
+}
+
+impl DocumentChanges {
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.title.is_none()
+            && self.description.is_none()
+            && self.language.is_none()
+    }
+
+    /// Mark the title, if it is changed, as set manually
+    #[must_use]
+    pub fn with_title_source(self) -> Self {
+        Self {
+            title_source: self.title.as_ref().map(|_| TitleSource::Manual),
+            ..self
+        }
+    }
 }
 
 #[derive(Debug, Queryable, Insertable)]
@@ -45,4 +148,53 @@ pub struct Keyword {
     pub word: String,
     pub occurrences: i32,
     pub document: String,
+    pub field: Field,
+    /// Form of the keyword as found in the document, if it differs
+    /// from its normalized form
+    pub form: Option<String>,
+}
+
+/// Keyword manually associated with a document
+///
+/// Unlike [`Keyword`], manual keywords are not extracted from the
+/// document’s content and are therefore kept when it is indexed
+/// again.
+#[derive(Debug, Queryable, Insertable)]
+pub struct ManualKeyword {
+    pub id: i32,
+    pub word: String,
+    pub weight: i32,
+    pub document: String,
+    /// Form of the keyword as given by the user, if it differs from
+    /// its normalized form
+    pub form: Option<String>,
+}
+
+/// Group of synonyms used to expand queries
+///
+/// Each of the `words` of the group is expanded to the other words of
+/// the group and to its `expansions`, but expansions are not expanded
+/// back. A group without expansions therefore holds words which are
+/// all synonyms of each other, while a group with a single word holds
+/// one-way expansions, such as `voiture` → `automobile`.
+#[derive(Debug, Queryable, Serialize, Clone, PartialEq, Eq)]
+#[serde(crate = "rocket::serde")]
+pub struct SynonymGroup {
+    pub id: i32,
+    /// ISO 639-1 code of the language of the group
+    pub language: String,
+    pub words: Vec<String>,
+    pub expansions: Vec<String>,
+}
+
+/// Synonym group to create, or changes to apply to one
+///
+/// Fields set to `None` are left untouched when updating a group.
+#[derive(Debug, Insertable, AsChangeset, Default, Deserialize, Clone)]
+#[diesel(table_name = synonyms)]
+#[serde(crate = "rocket::serde")]
+pub struct SynonymChanges {
+    pub language: Option<String>,
+    pub words: Option<Vec<String>>,
+    pub expansions: Option<Vec<String>>,
 }
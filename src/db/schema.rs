@@ -1,35 +1,92 @@
 // @generated automatically by Diesel CLI.
 
 pub mod sql_types {
-    #[derive(diesel::sql_types::SqlType)]
+    #[derive(diesel::query_builder::QueryId, diesel::sql_types::SqlType)]
     #[diesel(postgres_type(name = "documenttype"))]
     pub struct Documenttype;
+
+    #[derive(diesel::query_builder::QueryId, diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "keywordfield"))]
+    pub struct Keywordfield;
+
+    #[derive(diesel::query_builder::QueryId, diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "titlesource"))]
+    pub struct Titlesource;
 }
 
 diesel::table! {
     use diesel::sql_types::*;
     use super::sql_types::Documenttype;
+    use super::sql_types::Titlesource;
 
     documents (name) {
         name -> Varchar,
         title -> Varchar,
         doctype -> Documenttype,
         description -> Text,
+        language -> Nullable<Varchar>,
+        title_source -> Titlesource,
+    }
+}
+
+diesel::table! {
+    forms (document, form) {
+        document -> Varchar,
+        form -> Varchar,
+        occurrences -> Int4,
     }
 }
 
 diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::Keywordfield;
+
     keywords (id) {
         id -> Int4,
         word -> Varchar,
         occurrences -> Int4,
         document -> Varchar,
+        field -> Keywordfield,
+        form -> Nullable<Varchar>,
+    }
+}
+
+diesel::table! {
+    manual_keywords (id) {
+        id -> Int4,
+        word -> Varchar,
+        weight -> Int4,
+        document -> Varchar,
+        form -> Nullable<Varchar>,
+    }
+}
+
+diesel::table! {
+    stopwords (language, word) {
+        language -> Varchar,
+        word -> Varchar,
+        removed -> Bool,
+    }
+}
+
+diesel::table! {
+    synonyms (id) {
+        id -> Int4,
+        language -> Varchar,
+        words -> Array<Text>,
+        expansions -> Array<Text>,
     }
 }
 
+diesel::joinable!(forms -> documents (document));
 diesel::joinable!(keywords -> documents (document));
+diesel::joinable!(manual_keywords -> documents (document));
 
 diesel::allow_tables_to_appear_in_same_query!(
     documents,
+    forms,
     keywords,
+    manual_keywords,
+    stopwords,
+    synonyms,
 );
@@ -0,0 +1,132 @@
+use std::str::FromStr;
+
+use super::models::Field;
+
+/// Weight of keywords depending on the field they are found in
+///
+/// Weights are applied when ranking documents, so changing them does
+/// not require indexing documents again. A weight of zero ignores the
+/// field entirely.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldWeights {
+    pub title: i32,
+    pub description: i32,
+    pub heading: i32,
+    pub emphasis: i32,
+    pub body: i32,
+    pub keywords: i32,
+    pub boilerplate: i32,
+}
+
+impl Default for FieldWeights {
+    fn default() -> Self {
+        Self {
+            title: 5,
+            description: 3,
+            heading: 3,
+            emphasis: 2,
+            body: 1,
+            keywords: 2,
+            boilerplate: 0,
+        }
+    }
+}
+
+impl FieldWeights {
+    /// Weight of keywords found in `field`
+    #[must_use]
+    pub fn get(&self, field: Field) -> i32 {
+        match field {
+            Field::Title => self.title,
+            Field::Description => self.description,
+            Field::Heading => self.heading,
+            Field::Emphasis => self.emphasis,
+            Field::Body => self.body,
+            Field::Keywords => self.keywords,
+            Field::Boilerplate => self.boilerplate,
+        }
+    }
+
+    fn get_mut(&mut self, field: &str) -> Option<&mut i32> {
+        match field {
+            "title" => Some(&mut self.title),
+            "description" => Some(&mut self.description),
+            "heading" => Some(&mut self.heading),
+            "emphasis" => Some(&mut self.emphasis),
+            "body" => Some(&mut self.body),
+            "keywords" => Some(&mut self.keywords),
+            "boilerplate" => Some(&mut self.boilerplate),
+            _ => None,
+        }
+    }
+}
+
+/// Parse field weights from a list of `field=weight` pairs
+///
+/// Pairs are separated by commas, such as `title=5,body=1`. Fields
+/// which are not listed keep their default weight. At least one pair
+/// must be given.
+impl FromStr for FieldWeights {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.trim().is_empty() {
+            return Err("Expected at least one field=weight pair".into());
+        }
+        let mut weights = Self::default();
+        for pair in s.split(',').filter(|pair| !pair.trim().is_empty()) {
+            let (field, weight) = pair
+                .split_once('=')
+                .ok_or_else(|| format!("Expected field=weight, got {pair}"))?;
+            let weight = weight
+                .trim()
+                .parse::<i32>()
+                .map_err(|e| format!("Invalid weight for {field}: {e}"))?;
+            *weights
+                .get_mut(field.trim())
+                .ok_or_else(|| format!("Unknown field {field}"))? = weight;
+        }
+        Ok(weights)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn listed_fields_are_replaced() {
+        let weights = "title=10, body = 2".parse::<FieldWeights>().unwrap();
+        assert_eq!(
+            weights,
+            FieldWeights {
+                title: 10,
+                body: 2,
+                ..FieldWeights::default()
+            }
+        );
+    }
+
+    #[test]
+    fn zero_ignores_a_field() {
+        let weights = "boilerplate=0,emphasis=0".parse::<FieldWeights>();
+        assert_eq!(weights.unwrap().get(Field::Emphasis), 0);
+    }
+
+    #[test]
+    fn unknown_field_is_rejected() {
+        assert!("anchor=3".parse::<FieldWeights>().is_err());
+    }
+
+    #[test]
+    fn invalid_weight_is_rejected() {
+        assert!("title=high".parse::<FieldWeights>().is_err());
+        assert!("title".parse::<FieldWeights>().is_err());
+    }
+
+    #[test]
+    fn empty_list_is_rejected() {
+        assert!("".parse::<FieldWeights>().is_err());
+        assert!(" ".parse::<FieldWeights>().is_err());
+    }
+}
@@ -0,0 +1,145 @@
+use std::borrow::Cow;
+
+use chardetng::EncodingDetector;
+use encoding_rs::{Encoding, UTF_8};
+use tracing::debug;
+
+/// Amount of bytes in which `<meta>` charset declarations are looked
+/// for, as browsers do
+const PRESCAN_LENGTH: usize = 1024;
+
+/// Find the charset declared in a `Content-Type` value
+///
+/// For instance, `text/html; charset=ISO-8859-1` declares
+/// Windows-1252, which is a superset of ISO-8859-1.
+fn from_content_type(content_type: &str) -> Option<&'static Encoding> {
+    content_type.split(';').skip(1).find_map(|parameter| {
+        let (key, value) = parameter.split_once('=')?;
+        if key.trim().eq_ignore_ascii_case("charset") {
+            Encoding::for_label(value.trim().trim_matches('"').as_bytes())
+        } else {
+            None
+        }
+    })
+}
+
+/// Find the charset declared in the `<meta>` tags of an HTML document
+///
+/// Both `<meta charset="…">` and `<meta http-equiv="Content-Type"
+/// content="…; charset=…">` are supported. Only the first
+/// [`PRESCAN_LENGTH`] bytes of the document are read.
+///
+/// [`PRESCAN_LENGTH`]: ./constant.PRESCAN_LENGTH.html
+fn from_meta(doc: &[u8]) -> Option<&'static Encoding> {
+    let head = &doc[..doc.len().min(PRESCAN_LENGTH)];
+    let head = String::from_utf8_lossy(head).to_ascii_lowercase();
+    head.match_indices("<meta").find_map(|(start, _)| {
+        let tag = &head[start..];
+        let tag = &tag[..tag.find('>').unwrap_or(tag.len())];
+        let (_, value) = tag.split_once("charset")?;
+        let value = value.trim_start().strip_prefix('=')?;
+        let value = value.trim_start().trim_start_matches(['"', '\'']);
+        let end = value
+            .find(|c: char| c.is_whitespace() || "\"';/>".contains(c))
+            .unwrap_or(value.len());
+        // A document declaring UTF-16 in ASCII cannot be UTF-16
+        Encoding::for_label(&value.as_bytes()[..end])
+            .map(Encoding::output_encoding)
+    })
+}
+
+/// Guess the charset of a document from its content
+fn sniff(doc: &[u8]) -> &'static Encoding {
+    if std::str::from_utf8(doc).is_ok() {
+        return UTF_8;
+    }
+    let mut detector = EncodingDetector::new();
+    detector.feed(doc, true);
+    detector.guess(None, true)
+}
+
+/// Decode a textual document, such as an HTML document, to UTF-8
+///
+/// The charset of the document is, in order of precedence, the one
+/// given by its byte order mark, the one declared in `content_type`
+/// (usually the `Content-Type` HTTP header it was served with), the
+/// one declared in its `<meta>` tags for HTML documents, or the one
+/// guessed from its content. Invalid sequences are replaced with U+FFFD.
+#[must_use]
+pub fn decode<'a>(doc: &'a [u8], content_type: Option<&str>) -> Cow<'a, str> {
+    let encoding = content_type
+        .and_then(from_content_type)
+        .or_else(|| from_meta(doc))
+        .unwrap_or_else(|| sniff(doc));
+    let (text, encoding, malformed) = encoding.decode(doc);
+    debug!("====== Decoded document from {}", encoding.name());
+    if malformed {
+        debug!("====== Document contains malformed sequences");
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn content_type_charset_is_found() {
+        let encoding = from_content_type("text/html; charset=\"ISO-8859-1\"");
+        assert_eq!(encoding, Some(encoding_rs::WINDOWS_1252));
+        assert_eq!(from_content_type("text/html"), None);
+    }
+
+    #[test]
+    fn meta_charset_is_found() {
+        let doc = b"<html><head><META CHARSET='latin1'></head></html>";
+        assert_eq!(from_meta(doc), Some(encoding_rs::WINDOWS_1252));
+    }
+
+    #[test]
+    fn meta_http_equiv_charset_is_found() {
+        let doc = br#"<meta http-equiv="Content-Type"
+            content="text/html; charset=Shift_JIS">"#;
+        assert_eq!(from_meta(doc), Some(encoding_rs::SHIFT_JIS));
+    }
+
+    #[test]
+    fn meta_utf_16_declaration_means_utf_8() {
+        let doc = b"<meta charset=utf-16>";
+        assert_eq!(from_meta(doc), Some(UTF_8));
+    }
+
+    #[test]
+    fn meta_charset_after_prescan_is_ignored() {
+        let mut doc = vec![b' '; PRESCAN_LENGTH];
+        doc.extend_from_slice(b"<meta charset=latin1>");
+        assert_eq!(from_meta(&doc), None);
+    }
+
+    #[test]
+    fn content_type_takes_precedence_over_meta() {
+        let doc = b"<meta charset=utf-8><p>caf\xe9</p>";
+        let text = decode(doc, Some("text/html; charset=windows-1252"));
+        assert_eq!(text, "<meta charset=utf-8><p>caf\u{e9}</p>");
+    }
+
+    #[test]
+    fn byte_order_mark_takes_precedence() {
+        let doc = b"\xef\xbb\xbfcaf\xc3\xa9";
+        assert_eq!(decode(doc, Some("text/plain; charset=latin1")), "café");
+    }
+
+    #[test]
+    fn undeclared_legacy_encoding_is_sniffed() {
+        let doc = "Les élèves étudient à l’école où ils apprennent \
+                   le français, les mathématiques et l’éducation civique."
+            .replace('’', "'");
+        let (latin1, _, _) = encoding_rs::WINDOWS_1252.encode(&doc);
+        assert_eq!(decode(&latin1, None), doc);
+    }
+
+    #[test]
+    fn utf_8_is_decoded_as_is() {
+        assert_eq!(decode("café".as_bytes(), None), "café");
+    }
+}
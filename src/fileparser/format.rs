@@ -0,0 +1,266 @@
+use std::fmt::Display;
+use std::path::Path;
+use std::str::FromStr;
+
+use infer::MatcherType;
+use rocket::serde::json::{serde_json, Value};
+use tracing::{debug, info};
+
+use crate::fileparser::FileParsingError;
+
+/// Formats of the documents the indexer can parse
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Pdf,
+    Html,
+    Text,
+    Markdown,
+    Csv,
+    Json,
+}
+
+impl Format {
+    /// Format associated with a MIME type
+    ///
+    /// Parameters such as the charset are ignored. Generic types such
+    /// as `application/octet-stream` are not associated with any
+    /// format.
+    #[must_use]
+    pub fn from_mime(mime: &str) -> Option<Self> {
+        let mime = mime.split(';').next().unwrap_or_default().trim();
+        match mime.to_ascii_lowercase().as_str() {
+            "application/pdf" => Some(Self::Pdf),
+            "text/html" | "application/xhtml+xml" => Some(Self::Html),
+            "text/plain" => Some(Self::Text),
+            "text/markdown" | "text/x-markdown" => Some(Self::Markdown),
+            "text/csv" => Some(Self::Csv),
+            "application/json" => Some(Self::Json),
+            mime if mime.ends_with("+json") => Some(Self::Json),
+            _ => None,
+        }
+    }
+
+    /// Format associated with the extension of a file name or URL
+    #[must_use]
+    pub fn from_extension(name: &str) -> Option<Self> {
+        let path = name.split(['?', '#']).next().unwrap_or_default();
+        let extension = Path::new(path).extension()?.to_str()?;
+        match extension.to_ascii_lowercase().as_str() {
+            "pdf" => Some(Self::Pdf),
+            "html" | "htm" | "xhtml" => Some(Self::Html),
+            "txt" | "text" => Some(Self::Text),
+            "md" | "markdown" => Some(Self::Markdown),
+            "csv" => Some(Self::Csv),
+            "json" => Some(Self::Json),
+            _ => None,
+        }
+    }
+
+    /// Format detected from the magic bytes of a document
+    ///
+    /// Return `Err` if the document is recognized as a binary format
+    /// the indexer cannot parse, such as an image.
+    fn from_magic(doc: &[u8]) -> Result<Option<Self>, String> {
+        match infer::get(doc) {
+            None => Ok(None),
+            Some(kind) => match Self::from_mime(kind.mime_type()) {
+                Some(format) => Ok(Some(format)),
+                None if kind.matcher_type() == MatcherType::Text => Ok(None),
+                None => Err(kind.mime_type().to_string()),
+            },
+        }
+    }
+
+    /// Format guessed from the beginning of a textual document
+    ///
+    /// Return `None` if the document does not look like text.
+    fn sniff(doc: &[u8]) -> Option<Self> {
+        let head = &doc[..doc.len().min(1024)];
+        if head.contains(&0) {
+            return None;
+        }
+        let head = String::from_utf8_lossy(head);
+        let head = head.trim_start_matches('\u{feff}').trim_start();
+        let lowercase = head.to_lowercase();
+        if lowercase.starts_with("<!doctype html")
+            || lowercase.starts_with("<html")
+            || (lowercase.starts_with('<') && lowercase.contains("<body"))
+        {
+            Some(Self::Html)
+        } else if (head.starts_with('{') || head.starts_with('['))
+            && serde_json::from_slice::<Value>(doc).is_ok()
+        {
+            Some(Self::Json)
+        } else {
+            Some(Self::Text)
+        }
+    }
+
+    /// Detect the format of a document
+    ///
+    /// In order of precedence, the format is the one `requested` by
+    /// the user, the one recognized from the document’s magic bytes,
+    /// the one declared by `content_type`, the one associated with the
+    /// extension of `name`, or the one guessed from the document’s
+    /// content.
+    ///
+    /// # Errors
+    ///
+    /// If no format is detected, or if the magic bytes of the
+    /// document belong to an unsupported format, return a
+    /// [`FileParsingError`].
+    ///
+    /// [`FileParsingError`]: ../struct.FileParsingError.html
+    pub fn detect(
+        doc: &[u8],
+        requested: Option<Self>,
+        content_type: Option<&str>,
+        name: &str,
+    ) -> Result<Self, FileParsingError> {
+        if let Some(format) = requested {
+            info!("== Using requested format {format}");
+            return Ok(format);
+        }
+        let magic = Self::from_magic(doc).map_err(|mime| {
+            FileParsingError(format!("Mime type {mime} not supported"))
+        })?;
+        let candidates = [
+            ("magic bytes", magic),
+            ("content type", content_type.and_then(Self::from_mime)),
+            ("extension", Self::from_extension(name)),
+            ("content", Self::sniff(doc)),
+        ];
+        let (origin, format) = candidates
+            .into_iter()
+            .find_map(|(origin, format)| {
+                debug!("====== Format from {origin}: {format:?}");
+                format.map(|format| (origin, format))
+            })
+            .ok_or_else(|| FileParsingError("No format detected".into()))?;
+        info!("== Detected format {format} from {origin}");
+        Ok(format)
+    }
+}
+
+impl Display for Format {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Pdf => "pdf",
+            Self::Html => "html",
+            Self::Text => "text",
+            Self::Markdown => "markdown",
+            Self::Csv => "csv",
+            Self::Json => "json",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "pdf" => Ok(Self::Pdf),
+            "html" => Ok(Self::Html),
+            "text" | "txt" => Ok(Self::Text),
+            "markdown" | "md" => Ok(Self::Markdown),
+            "csv" => Ok(Self::Csv),
+            "json" => Ok(Self::Json),
+            _ => Err(format!("Unsupported format {s}")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mime_parameters_are_ignored() {
+        let format = Format::from_mime("Text/HTML; charset=UTF-8");
+        assert_eq!(format, Some(Format::Html));
+        assert_eq!(
+            Format::from_mime("application/ld+json"),
+            Some(Format::Json)
+        );
+        assert_eq!(Format::from_mime("application/octet-stream"), None);
+    }
+
+    #[test]
+    fn extension_ignores_query_and_fragment() {
+        let name = "https://example.org/notes.MD?download=1#top";
+        assert_eq!(Format::from_extension(name), Some(Format::Markdown));
+        assert_eq!(Format::from_extension("archive.tar.gz"), None);
+        assert_eq!(Format::from_extension("README"), None);
+    }
+
+    #[test]
+    fn requested_format_takes_precedence() {
+        let format = Format::detect(b"%PDF-1.4", Some(Format::Text), None, "");
+        assert_eq!(format.unwrap(), Format::Text);
+    }
+
+    #[test]
+    fn magic_bytes_take_precedence_over_content_type() {
+        let format = Format::detect(
+            b"%PDF-1.4\n",
+            None,
+            Some("text/html"),
+            "document.html",
+        );
+        assert_eq!(format.unwrap(), Format::Pdf);
+    }
+
+    #[test]
+    fn unsupported_binary_format_is_rejected() {
+        let png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+        assert!(Format::detect(png, None, None, "image.txt").is_err());
+    }
+
+    #[test]
+    fn content_type_takes_precedence_over_extension() {
+        let format =
+            Format::detect(b"# Title", None, Some("text/plain"), "notes.md");
+        assert_eq!(format.unwrap(), Format::Text);
+    }
+
+    #[test]
+    fn extension_is_used_without_content_type() {
+        let format = Format::detect(b"a,b\n1,2\n", None, None, "data.csv");
+        assert_eq!(format.unwrap(), Format::Csv);
+    }
+
+    #[test]
+    fn content_is_sniffed_last() {
+        let html = b"\xef\xbb\xbf  <!DOCTYPE html><html></html>";
+        assert_eq!(
+            Format::detect(html, None, None, "page").unwrap(),
+            Format::Html
+        );
+        let json = br#"{"title": "Notes"}"#;
+        assert_eq!(
+            Format::detect(json, None, None, "data").unwrap(),
+            Format::Json
+        );
+        let text = b"{ not json";
+        assert_eq!(
+            Format::detect(text, None, None, "notes").unwrap(),
+            Format::Text
+        );
+    }
+
+    #[test]
+    fn format_names_round_trip() {
+        for format in [
+            Format::Pdf,
+            Format::Html,
+            Format::Text,
+            Format::Markdown,
+            Format::Csv,
+            Format::Json,
+        ] {
+            assert_eq!(format.to_string().parse::<Format>(), Ok(format));
+        }
+    }
+}
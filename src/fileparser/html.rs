@@ -1,8 +1,34 @@
+use std::collections::HashMap;
 use std::fmt::Debug;
 
-use crate::fileparser::{FileParsingError, ParsingResult};
+use crate::db::models::TitleSource;
+use crate::fileparser::{
+    charset, clean_title, FileParsingError, ParsedFile, ParsedTitle,
+    ParsingResult,
+};
+use ego_tree::NodeId;
 use scraper::{ElementRef, Html, Selector};
-use tracing::info;
+use tracing::{debug, info};
+
+/// Elements whose content is never part of a document’s main content
+const BOILERPLATE_SELECTOR: &str = "nav, footer, aside, form, script, style, \
+     noscript, iframe, [role=navigation], [role=banner], \
+     [role=contentinfo], [role=complementary], [aria-hidden=true]";
+
+/// Elements explicitly holding a document’s main content
+const MAIN_CONTENT_SELECTOR: &str = "main, article, [role=main]";
+
+/// Elements holding paragraphs of text
+const PARAGRAPH_SELECTOR: &str = "p, pre, blockquote";
+
+/// Elements holding headings of the main content, of level 1 to 3
+const HEADING_SELECTOR: &str = "h1, h2, h3";
+
+/// Elements holding emphasized text
+const EMPHASIS_SELECTOR: &str = "strong, b, em, i, mark";
+
+/// Minimum length in characters of a paragraph to be scored
+const MIN_PARAGRAPH_LENGTH: usize = 25;
 
 #[derive(Debug)]
 enum HtmlParsingError {
@@ -24,8 +50,7 @@ macro_rules! make_selector {
     };
 }
 
-fn get_title(document: &Html) -> Result<String, HtmlParsingError> {
-    info!("== HTML: Parsing title");
+fn get_title_tag(document: &Html) -> Result<String, HtmlParsingError> {
     let selector = make_selector!("title");
     document.select(&selector).next().map_or_else(
         || {
@@ -48,6 +73,48 @@ fn get_title(document: &Html) -> Result<String, HtmlParsingError> {
     )
 }
 
+fn get_open_graph_title(document: &Html) -> Result<String, HtmlParsingError> {
+    match get_simple_tag(document, r#"meta[property="og:title"]"#) {
+        Ok(title) => match title.value().attr("content") {
+            Some(val) => Ok(val.to_string()),
+            None => Err(HtmlParsingError::ElementAttrNotFound(
+                "Could not find content of og:title".to_string(),
+            )),
+        },
+        Err(e) => Err(e),
+    }
+}
+
+fn get_first_heading(document: &Html) -> Result<String, HtmlParsingError> {
+    get_simple_tag(document, HEADING_SELECTOR)
+        .map(|heading| heading.text().collect::<String>())
+}
+
+type TitleGetter = fn(&Html) -> Result<String, HtmlParsingError>;
+
+/// Find the title of an HTML document
+///
+/// Try in order the `<title>` tag, the `og:title` Open Graph
+/// property, and the first heading of the document. Return `None` if
+/// none of them are present or if they are empty.
+fn get_title(document: &Html) -> ParsedTitle {
+    info!("== HTML: Parsing title");
+    let candidates: [(TitleGetter, TitleSource); 3] = [
+        (get_title_tag, TitleSource::Metadata),
+        (get_open_graph_title, TitleSource::OpenGraph),
+        (get_first_heading, TitleSource::Heading),
+    ];
+    candidates
+        .iter()
+        .find_map(|(get, source)| match get(document) {
+            Ok(title) => clean_title(&title).map(|title| (title, *source)),
+            Err(e) => {
+                info!("== HTML: No title found: {e:?}");
+                None
+            }
+        })
+}
+
 fn get_keywords(document: &Html) -> Result<Vec<String>, HtmlParsingError> {
     info!("== HTML: Parsing keywords");
     let selector = make_selector!(r#"meta[name="keywords"]"#);
@@ -64,16 +131,169 @@ fn get_keywords(document: &Html) -> Result<Vec<String>, HtmlParsingError> {
     Ok(keywords)
 }
 
+/// Find the language declared by an HTML document
+///
+/// The `lang` attribute of the `<html>` tag is preferred over the
+/// `Content-Language` HTTP equivalent.
+fn get_language(document: &Html) -> Option<String> {
+    info!("== HTML: Parsing language");
+    get_simple_tag(document, "html[lang]")
+        .ok()
+        .and_then(|html| html.value().attr("lang"))
+        .or_else(|| {
+            get_simple_tag(document, r#"meta[http-equiv="content-language"]"#)
+                .ok()
+                .and_then(|meta| meta.value().attr("content"))
+        })
+        .map(ToString::to_string)
+}
+
+/// Convert an HTML snippet to plain text
+fn to_text(html: &str) -> String {
+    let decorator = html2text::render::text_renderer::TrivialDecorator::new();
+    html2text::from_read_with_decorator(html.as_bytes(), html.len(), decorator)
+}
+
 fn get_body(document: &Html) -> Result<String, HtmlParsingError> {
-    get_simple_tag(document, "body").map(|body| {
-        let decorator =
-            html2text::render::text_renderer::TrivialDecorator::new();
-        html2text::from_read_with_decorator(
-            body.inner_html().as_bytes(),
-            body.inner_html().len(),
-            decorator,
-        )
-    })
+    get_simple_tag(document, "body").map(|body| to_text(&body.inner_html()))
+}
+
+/// Text of all elements of `document` matching `selector`, one per line
+fn get_all_text(
+    document: &Html,
+    selector: &str,
+) -> Result<String, HtmlParsingError> {
+    let selector = make_selector!(selector);
+    Ok(document
+        .select(&selector)
+        .map(|element| element.text().collect::<String>())
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+/// Length in characters of the text held by `element`
+fn text_length(element: &ElementRef) -> usize {
+    element.text().map(|text| text.chars().count()).sum()
+}
+
+/// Length in characters of the text held by links in `element`
+///
+/// `links` must be a selector for `a` elements.
+fn link_text_length(element: &ElementRef, links: &Selector) -> usize {
+    element.select(links).map(|link| text_length(&link)).sum()
+}
+
+/// Remove boilerplate elements from the document
+///
+/// Elements matching [`BOILERPLATE_SELECTOR`], such as navigation
+/// menus, footers, and sidebars, are detached from the document.
+/// Return their text. Elements nested in another boilerplate element,
+/// such as a form in a footer, are part of its text and are not
+/// repeated.
+///
+/// [`BOILERPLATE_SELECTOR`]: ./constant.BOILERPLATE_SELECTOR.html
+fn remove_boilerplate(document: &mut Html) -> Result<String, HtmlParsingError> {
+    info!("== HTML: Removing boilerplate");
+    let selector = make_selector!(BOILERPLATE_SELECTOR);
+    let mut ids = Vec::new();
+    let mut text = Vec::new();
+    // Elements are selected in document order, so ancestors are
+    // always matched before their descendants
+    for element in document.select(&selector) {
+        if !element
+            .ancestors()
+            .any(|ancestor| ids.contains(&ancestor.id()))
+        {
+            ids.push(element.id());
+            text.push(to_text(&element.html()));
+        }
+    }
+    for id in ids {
+        if let Some(mut node) = document.tree.get_mut(id) {
+            node.detach();
+        }
+    }
+    Ok(text.join("\n"))
+}
+
+/// Find the element holding the main content of a document
+///
+/// If the document explicitly declares its main content, the
+/// declared element with the most text is chosen. Otherwise, each
+/// paragraph of text scores its parent and, to a lesser extent, its
+/// grandparent depending on its length and its amount of commas.
+/// Scores are then penalized by the link density of the element, so
+/// lists of links such as menus are not chosen. The element with the
+/// highest score is chosen.
+///
+/// Return `None` if no element holds paragraphs of text.
+fn get_main_content(
+    document: &Html,
+) -> Result<Option<NodeId>, HtmlParsingError> {
+    let selector = make_selector!(MAIN_CONTENT_SELECTOR);
+    if let Some(main) = document.select(&selector).max_by_key(text_length) {
+        debug!("====== HTML: Found explicit main content");
+        return Ok(Some(main.id()));
+    }
+
+    let selector = make_selector!(PARAGRAPH_SELECTOR);
+    let mut scores: HashMap<NodeId, usize> = HashMap::new();
+    for paragraph in document.select(&selector) {
+        let length = text_length(&paragraph);
+        if length < MIN_PARAGRAPH_LENGTH {
+            continue;
+        }
+        let commas = paragraph
+            .text()
+            .map(|text| text.matches(',').count())
+            .sum::<usize>();
+        let score = 1 + commas + length / 100;
+        if let Some(parent) = paragraph.parent() {
+            *scores.entry(parent.id()).or_default() += score * 2;
+            if let Some(grandparent) = parent.parent() {
+                *scores.entry(grandparent.id()).or_default() += score;
+            }
+        }
+    }
+
+    let links = make_selector!("a");
+    let best = scores
+        .into_iter()
+        .filter_map(|(id, score)| {
+            let element = document.tree.get(id).and_then(ElementRef::wrap)?;
+            let length = text_length(&element).max(1);
+            let link_length = link_text_length(&element, &links).min(length);
+            Some((id, score * (length - link_length) / length))
+        })
+        .max_by_key(|(_, score)| *score);
+    Ok(best.map(|(id, _)| id))
+}
+
+/// Extract the main content of a document
+///
+/// The main content is found with [`get_main_content`] in a document
+/// whose boilerplate was already removed. Return the text of the main
+/// content, and the text of everything else in the document’s body.
+/// If no main content is found, the whole body is considered to be
+/// the main content.
+///
+/// [`get_main_content`]: ./fn.get_main_content.html
+fn extract_content(
+    mut document: Html,
+) -> Result<(String, String), HtmlParsingError> {
+    info!("== HTML: Extracting main content");
+    let main = match get_main_content(&document)?
+        .and_then(|id| document.tree.get(id))
+        .and_then(ElementRef::wrap)
+    {
+        Some(main) => (main.id(), to_text(&main.html())),
+        None => return Ok((get_body(&document)?, String::new())),
+    };
+    if let Some(mut node) = document.tree.get_mut(main.0) {
+        node.detach();
+    }
+    // The main content may have been the body itself
+    Ok((main.1, get_body(&document).unwrap_or_default()))
 }
 
 fn get_description(document: &Html) -> Result<String, HtmlParsingError> {
@@ -106,8 +326,14 @@ fn get_simple_tag<'r>(
 
 /// Parse an HTML file
 ///
-/// Receive an HTML file’s content raw, extract from it its title,
-/// keywords, description, and text.
+/// Receive an HTML file’s content raw, decode it to UTF-8 using the
+/// charset declared in `content_type` or in the document itself (see
+/// [`charset::decode`]), and extract from it its title, keywords,
+/// description, language, headings, emphasized text, and text. Only
+/// the text of the document’s main content is considered to be its
+/// body, the rest is boilerplate, see [`extract_content`]. Headings
+/// and emphasized text are looked for outside of boilerplate elements
+/// only.
 ///
 /// # Errors
 ///
@@ -115,20 +341,31 @@ fn get_simple_tag<'r>(
 /// function. For more information, see [`PdfParsingError`].
 ///
 /// [`HtmlParsingError`]: ./struct.HtmlParsingError.html
-pub fn parse(doc: &[u8]) -> ParsingResult {
-    let html_string = match std::str::from_utf8(doc) {
-        Ok(v) => v,
-        Err(e) => {
-            return Err(FileParsingError(format!(
-                "Could not convert input data to string: {e}"
-            )))
-        }
-    };
-    let html = scraper::Html::parse_document(html_string);
-    let title = get_title(&html).map_err(FileParsingError::new)?;
+/// [`extract_content`]: ./fn.extract_content.html
+/// [`charset::decode`]: ../charset/fn.decode.html
+pub fn parse(doc: &[u8], content_type: Option<&str>) -> ParsingResult {
+    let html_string = charset::decode(doc, content_type);
+    let mut html = scraper::Html::parse_document(&html_string);
+    let title = get_title(&html);
     let keywords = get_keywords(&html).map_err(FileParsingError::new)?;
-    let body = get_body(&html).map_err(FileParsingError::new)?;
-    let subject = get_description(&html).ok();
-    info!("====== Subject of file: {subject:?}");
-    Ok((title, keywords, body, subject))
+    let description = get_description(&html).ok();
+    info!("====== Subject of file: {description:?}");
+    let language = get_language(&html);
+    let boilerplate =
+        remove_boilerplate(&mut html).map_err(FileParsingError::new)?;
+    let headings =
+        get_all_text(&html, HEADING_SELECTOR).map_err(FileParsingError::new)?;
+    let emphasis = get_all_text(&html, EMPHASIS_SELECTOR)
+        .map_err(FileParsingError::new)?;
+    let (body, rest) = extract_content(html).map_err(FileParsingError::new)?;
+    Ok(ParsedFile {
+        title,
+        keywords,
+        body,
+        headings,
+        emphasis,
+        boilerplate: [boilerplate, rest].join("\n"),
+        description,
+        language,
+    })
 }
@@ -1,7 +1,24 @@
+use std::collections::HashMap;
 use std::fmt::Debug;
 
+use tracing::info;
+
+use crate::db::models::{Field, TitleSource};
+use crate::kwparser::Term;
+use crate::language::{self, Languages};
+
+pub mod charset;
+pub mod format;
 pub mod html;
 pub mod pdf;
+pub mod summary;
+pub mod text;
+
+use format::Format;
+
+/// Maximum length in characters of a title not found in a document’s
+/// metadata
+pub const MAX_TITLE_LENGTH: usize = 80;
 
 #[derive(Debug)]
 pub struct FileParsingError(String);
@@ -18,53 +35,204 @@ impl FileParsingError {
 #[derive(Debug)]
 pub struct ParsedDocument {
     pub title: String,
-    pub keywords: Vec<String>,
-    pub content: Vec<String>,
+    pub title_source: TitleSource,
+    /// Keywords of the document along with the field they were found
+    /// in
+    pub fields: Vec<(Field, Vec<Term>)>,
     pub description: String,
+    /// ISO 639-1 code of the language of the document, if known
+    pub language: Option<String>,
+    /// Forms of the words of the document, stop words included, along
+    /// with their number of occurrences, see [`Analyzer::forms`]
+    ///
+    /// [`Analyzer::forms`]: ../analyzer/struct.Analyzer.html#method.forms
+    pub forms: HashMap<String, i32>,
+}
+
+pub type ParsedTitle = Option<(String, TitleSource)>;
+
+/// Raw content of a parsed file
+#[derive(Debug, Default)]
+pub struct ParsedFile {
+    pub title: ParsedTitle,
+    pub keywords: Vec<String>,
+    /// Main text of the file
+    pub body: String,
+    /// Text of the headings of the main text
+    pub headings: String,
+    /// Text emphasized in the main text
+    pub emphasis: String,
+    /// Text surrounding the main text, such as navigation menus and
+    /// footers
+    pub boilerplate: String,
+    pub description: Option<String>,
+    /// Language declared by the file, as a language tag
+    pub language: Option<String>,
 }
 
-pub type ParsedFile = (String, Vec<String>, String, Option<String>);
+/// Clean up a candidate title
+///
+/// Collapse its whitespace and return it if it is not empty.
+#[must_use]
+pub fn clean_title(title: &str) -> Option<String> {
+    let title = title.split_whitespace().collect::<Vec<_>>().join(" ");
+    if title.is_empty() {
+        None
+    } else {
+        Some(title)
+    }
+}
+
+/// Find the title of a document from its first line of text
+///
+/// The line is truncated to [`MAX_TITLE_LENGTH`] characters.
+///
+/// [`MAX_TITLE_LENGTH`]: ./constant.MAX_TITLE_LENGTH.html
+fn get_first_line(text: &str) -> Option<String> {
+    text.lines()
+        .find_map(clean_title)
+        .map(|line| line.chars().take(MAX_TITLE_LENGTH).collect())
+}
 pub type ParsingResult = Result<ParsedFile, FileParsingError>;
 
+/// Parse the raw content of a file
+///
+/// The format of the file is the `requested` one if any, otherwise it
+/// is detected from the file, `content_type`, and the extension of
+/// `name`, see [`Format::detect`]. `content_type` may also declare
+/// the charset of the file.
+///
+/// # Errors
+///
+/// If no supported format is detected, or if the file cannot be
+/// parsed, return a [`FileParsingError`].
+///
+/// [`FileParsingError`]: ./struct.FileParsingError.html
+/// [`Format::detect`]: ./format/enum.Format.html#method.detect
+pub fn parse(
+    doc: &[u8],
+    content_type: Option<&str>,
+    requested: Option<Format>,
+    name: &str,
+) -> ParsingResult {
+    match Format::detect(doc, requested, content_type, name)? {
+        Format::Pdf => pdf::parse(doc),
+        Format::Html => html::parse(doc, content_type),
+        Format::Text => Ok(text::parse_text(doc, content_type)),
+        Format::Markdown => Ok(text::parse_markdown(doc, content_type)),
+        Format::Csv => text::parse_csv(doc, content_type),
+        Format::Json => text::parse_json(doc, content_type),
+    }
+}
+
 /// Get the body of a file
 ///
 /// Get from the raw content of a file the text from its body.
+/// `content_type` is the MIME type the file was served or uploaded
+/// with, if any, which may declare the charset of HTML documents.
+///
+/// The file is parsed according to its format, see [`parse`].
+///
+/// Keywords are extracted separately from each field of the document,
+/// so they can be weighted depending on where they were found. The
+/// forms of its words are counted as well, so dictionaries can learn
+/// them, see [`db::vocabulary`]. They are counted once, over its body,
+/// its boilerplate, and the title, description, and keywords found in
+/// its metadata: headings and emphasized text are part of its body,
+/// while titles found from its text and generated descriptions are
+/// copies of it.
+///
+/// If the parser cannot find a title in the document, its first line
+/// of text is used instead, or `name` if it has no text. Which one
+/// was chosen is stored in the title source of the returned
+/// [`ParsedDocument`].
+///
+/// The language of the document is the one it declares, or the one
+/// detected from its text. Its keywords are then extracted with the
+/// pipeline of its language, see [`Languages::pipeline`].
+///
+/// If the document has no description, a summary of at most
+/// `description_length` characters is generated from its text,
+/// skipping the boilerplate phrases of its language, see
+/// [`summary::summarize`].
 ///
 /// # Errors
 ///
-/// If no mime type is detected, return a [`FileParsingError`].
+/// If no supported format is detected, or if the file cannot be
+/// parsed, return a [`FileParsingError`].
 ///
 /// [`FileParsingError`]: ./struct.FileParsingError.html
+/// [`parse`]: ./fn.parse.html
+/// [`ParsedDocument`]: ./struct.ParsedDocument.html
+/// [`summary::summarize`]: ./summary/fn.summarize.html
+/// [`Languages::pipeline`]: ../language/struct.Languages.html#method.pipeline
+/// [`db::vocabulary`]: ../db/fn.vocabulary.html
 pub fn get_content(
     doc: &[u8],
-    stop_words: &[String],
-    glaff: &Option<crate::kwparser::Glaff>,
+    content_type: Option<&str>,
+    requested: Option<Format>,
+    name: &str,
+    description_length: usize,
+    languages: &Languages,
 ) -> Result<ParsedDocument, FileParsingError> {
-    use crate::kwparser::get_keywords_from_text;
-
-    let content = match infer::get(doc) {
-        None => Err(FileParsingError("No mime type detected".into())),
-        Some(mime) => match mime.mime_type() {
-            "application/pdf" => pdf::parse(doc),
-            "text/html" => html::parse(doc),
-            mime => {
-                Err(FileParsingError(format!("Mime type {mime} not supported")))
-            }
-        },
-    };
-    let content = match content {
-        Ok(val) => val,
-        Err(e) => {
-            return Err(e);
-        }
-    };
-    let keywords = get_keywords_from_text(&content.2, stop_words, glaff);
+    let content = parse(doc, content_type, requested, name)?;
+    let (title, title_source) = content
+        .title
+        .or_else(|| {
+            get_first_line(&content.body)
+                .map(|line| (line, TitleSource::FirstLine))
+        })
+        .unwrap_or_else(|| (name.to_string(), TitleSource::Name));
+    let language = content
+        .language
+        .as_deref()
+        .and_then(language::normalize_tag)
+        .or_else(|| language::detect(&content.body));
+    info!("== Language of the document: {language:?}");
+    let pipeline = languages.pipeline(language.as_deref());
+    // Copies of the text of the document are left out of its forms
+    let mut sources = vec![content.body.as_str(), content.boilerplate.as_str()];
+    if matches!(title_source, TitleSource::Metadata | TitleSource::OpenGraph) {
+        sources.push(&title);
+    }
+    if let Some(description) = &content.description {
+        sources.push(description);
+    }
+    let keywords = content.keywords.join(" ");
+    sources.push(&keywords);
+    let mut forms: HashMap<String, i32> = HashMap::new();
+    for form in sources
+        .into_iter()
+        .flat_map(|text| pipeline.analyzer.forms(text))
+    {
+        *forms.entry(form).or_default() += 1;
+    }
+    let description = content.description.unwrap_or_else(|| {
+        summary::summarize(
+            &content.body,
+            description_length,
+            &pipeline.boilerplate,
+        )
+    });
+    let texts = [
+        (Field::Title, title.as_str()),
+        (Field::Description, description.as_str()),
+        (Field::Heading, content.headings.as_str()),
+        (Field::Emphasis, content.emphasis.as_str()),
+        (Field::Body, content.body.as_str()),
+        (Field::Keywords, keywords.as_str()),
+        (Field::Boilerplate, content.boilerplate.as_str()),
+    ];
+    let fields = texts
+        .iter()
+        .map(|(field, text)| (*field, pipeline.analyzer.keywords(text)))
+        .collect();
     Ok(ParsedDocument {
-        title: content.0,
-        keywords: content.1,
-        content: keywords,
-        description: content
-            .3
-            .unwrap_or_else(|| content.2[..120].replace('\n', " ")),
+        title,
+        title_source,
+        fields,
+        description,
+        language,
+        forms,
     })
 }
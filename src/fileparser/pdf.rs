@@ -1,17 +1,81 @@
-use poppler::Document;
+use std::collections::HashMap;
+
+use poppler::{Document, Page};
 use tracing::{debug, info};
 
-use crate::fileparser::{FileParsingError, ParsingResult};
+use crate::db::models::TitleSource;
+use crate::fileparser::{
+    clean_title, FileParsingError, ParsedFile, ParsedTitle, ParsingResult,
+    MAX_TITLE_LENGTH,
+};
 
-#[derive(Debug)]
-struct PdfParsingError(String);
+/// Find the heading of a page
+///
+/// The heading is the first block of text set in the largest font of
+/// the page, provided this font is larger than the one of most of the
+/// text of the page and the heading is short enough to be a title.
+fn get_heading(page: &Page) -> Option<String> {
+    let text = page.text()?.chars().collect::<Vec<char>>();
+    let spans = page
+        .text_attributes()
+        .iter()
+        .filter_map(|attributes| {
+            // SAFETY: `attributes` wraps a valid `PopplerTextAttributes`
+            // owned by the list returned by Poppler.
+            let attributes = unsafe { &*attributes.as_ptr() };
+            let start = usize::try_from(attributes.start_index).ok()?;
+            let end = usize::try_from(attributes.end_index).ok()?;
+            text.get(start..=end)
+                .filter(|span| span.iter().any(|c| c.is_alphanumeric()))
+                .map(|span| (attributes.font_size, start, span))
+        })
+        .collect::<Vec<(f64, usize, &[char])>>();
+    let largest = spans.iter().map(|(size, ..)| *size).fold(0.0, f64::max);
+    let mut sizes: HashMap<u64, usize> = HashMap::new();
+    for (size, _, span) in &spans {
+        *sizes.entry(size.to_bits()).or_default() += span.len();
+    }
+    let body = sizes
+        .into_iter()
+        .max_by_key(|(_, length)| *length)
+        .map(|(size, _)| f64::from_bits(size))?;
+    if largest <= body {
+        return None;
+    }
+    let mut heading = String::new();
+    let mut end = None;
+    for (_, start, span) in spans.iter().filter(|(size, ..)| *size >= largest) {
+        if let Some(end) = end {
+            // Only keep the first block of text in the largest font
+            let gap = text.get(end..*start).unwrap_or_default();
+            if gap.iter().any(|c| !c.is_whitespace()) {
+                break;
+            }
+            heading.push(' ');
+        }
+        heading.extend(*span);
+        end = Some(start + span.len());
+    }
+    clean_title(&heading)
+        .filter(|heading| heading.chars().count() <= MAX_TITLE_LENGTH)
+}
 
-fn get_title(doc: &Document) -> Result<String, PdfParsingError> {
+/// Find the title of a PDF
+///
+/// The title declared in the PDF’s metadata is preferred. Otherwise,
+/// the heading of the first page is used as the title, see
+/// [`get_heading`].
+///
+/// [`get_heading`]: ./fn.get_heading.html
+fn get_title(doc: &Document) -> ParsedTitle {
     info!("=== PDF: Parsing title");
-    doc.title().map_or_else(
-        || Err(PdfParsingError("Could not get title".into())),
-        |title| Ok(title.into()),
-    )
+    if let Some(title) = doc.title().and_then(|title| clean_title(&title)) {
+        return Some((title, TitleSource::Metadata));
+    }
+    info!("=== PDF: No title in metadata, looking for a heading");
+    doc.page(0)
+        .and_then(|page| get_heading(&page))
+        .map(|heading| (heading, TitleSource::Heading))
 }
 
 fn get_keywords(doc: &Document) -> Vec<String> {
@@ -39,6 +103,27 @@ fn get_body(doc: &Document) -> String {
     body
 }
 
+/// Find the language declared in the metadata of a PDF
+///
+/// Poppler does not expose the `/Lang` entry of the document catalog,
+/// so the language is read from the `dc:language` property of the
+/// XMP metadata of the PDF, which producers fill with the same
+/// language. The property is either a single value or a list, whose
+/// first item is used.
+fn get_language(doc: &Document) -> Option<String> {
+    info!("=== PDF: Parsing language");
+    let metadata = doc.metadata()?;
+    let property = &metadata[metadata.find("<dc:language")?..];
+    let value = property[..property.find("</dc:language>")?]
+        .split('>')
+        .skip(1)
+        .filter_map(|content| content.split('<').next())
+        .map(str::trim)
+        .find(|content| !content.is_empty())?;
+    debug!("====== PDF: Language: {value}");
+    crate::language::normalize_tag(value)
+}
+
 fn get_subject(doc: &Document) -> Option<String> {
     info!("=== PDF: Parsing subject");
     doc.subject().map(|e| e.to_string())
@@ -47,22 +132,25 @@ fn get_subject(doc: &Document) -> Option<String> {
 /// Parse a PDF file
 ///
 /// Receive a PDF file’s content raw, extract from it its title,
-/// keywords, subject, and text.
+/// keywords, subject, language, and text. The title may be missing.
 ///
 /// # Errors
 ///
-/// If any error occurs when parsing the PDF, return it to the caller
-/// function. For more information, see [`PdfParsingError`].
+/// If the PDF cannot be read, return a [`FileParsingError`] to the
+/// caller function.
 ///
-/// [`PdfParsingError`]: ./struct.PdfParsingError.html
-pub fn parse(doc: &[u8]) -> ParsingResult {
+/// [`FileParsingError`]: ../struct.FileParsingError.html
+pub fn parse(raw: &[u8]) -> ParsingResult {
     info!("== PDF: Parsing document");
-    let doc = poppler::Document::from_data(doc, None).map_err(|e| {
+    let doc = poppler::Document::from_data(raw, None).map_err(|e| {
         FileParsingError::new(format!("Failed to parse PDF: {e:?}"))
     })?;
-    let title = get_title(&doc).map_err(FileParsingError::new)?;
-    let keywords = get_keywords(&doc);
-    let body = get_body(&doc);
-    let subject = get_subject(&doc);
-    Ok((title, keywords, body, subject))
+    Ok(ParsedFile {
+        title: get_title(&doc),
+        keywords: get_keywords(&doc),
+        body: get_body(&doc),
+        description: get_subject(&doc),
+        language: get_language(&doc),
+        ..Default::default()
+    })
 }
@@ -0,0 +1,150 @@
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Minimum amount of words a line must have to be part of a summary
+///
+/// Shorter lines are usually navigation items, buttons, or headings.
+const MIN_WORDS: usize = 5;
+
+/// Determine if a line of text is navigation or boilerplate text
+///
+/// `phrases` are lowercase snippets of text commonly found in
+/// boilerplate lines, such as copyright notices.
+fn is_boilerplate(line: &str, phrases: &[String]) -> bool {
+    let line = line.to_lowercase();
+    line.unicode_words().count() < MIN_WORDS
+        || phrases.iter().any(|phrase| line.contains(phrase.as_str()))
+}
+
+/// Length of `text` in user-perceived characters
+fn length(text: &str) -> usize {
+    text.graphemes(true).count()
+}
+
+/// Truncate `text` on a word boundary, appending an ellipsis
+///
+/// The result, ellipsis included, is at most `max_length` characters
+/// long. If the first word of `text` is already too long, `text` is
+/// cut on a grapheme boundary instead.
+fn truncate_words(text: &str, max_length: usize) -> String {
+    let max_length = max_length.saturating_sub(1);
+    let mut result = String::new();
+    let mut result_length = 0;
+    for word in text.split_word_bounds() {
+        let word_length = length(word);
+        if result_length + word_length > max_length {
+            break;
+        }
+        result.push_str(word);
+        result_length += word_length;
+    }
+    if result.trim().is_empty() {
+        result = text.graphemes(true).take(max_length).collect();
+    }
+    format!("{}…", result.trim_end())
+}
+
+/// Generate a summary of `text`
+///
+/// Navigation lines and lines containing one of the boilerplate
+/// `phrases` of the language of the text are skipped, unless the
+/// whole text is made of them. The summary is made of the first sentences
+/// of the remaining text, as long as they fit in `max_length`
+/// characters. If the first sentence alone is too long, it is
+/// truncated on a word boundary.
+///
+/// Lengths are counted in graphemes, so multi-byte characters and
+/// combining characters are never split.
+#[must_use]
+pub fn summarize(text: &str, max_length: usize, phrases: &[String]) -> String {
+    let content = text
+        .lines()
+        .filter(|line| !is_boilerplate(line, phrases))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let content = if content.trim().is_empty() {
+        text
+    } else {
+        &content
+    };
+    let content = content.split_whitespace().collect::<Vec<_>>().join(" ");
+    if length(&content) <= max_length {
+        return content;
+    }
+
+    let mut summary = String::new();
+    let mut summary_length = 0;
+    for sentence in content.split_sentence_bounds() {
+        let sentence_length = length(sentence);
+        if summary_length + sentence_length > max_length {
+            break;
+        }
+        summary.push_str(sentence);
+        summary_length += sentence_length;
+    }
+    if summary.trim().is_empty() {
+        truncate_words(&content, max_length)
+    } else {
+        summary.trim_end().to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_text_is_kept_whole() {
+        let text = "Une phrase assez longue pour un résumé.";
+        assert_eq!(summarize(text, 280, &[]), text);
+    }
+
+    #[test]
+    fn summary_stops_on_sentence_boundary() {
+        let text = "La première phrase est courte. La seconde phrase est \
+                    bien plus longue que la première.";
+        assert_eq!(summarize(text, 40, &[]), "La première phrase est courte.");
+    }
+
+    #[test]
+    fn long_sentence_is_truncated_on_word_boundary() {
+        let text = "Une seule phrase bien trop longue pour tenir dans le \
+                    résumé demandé";
+        let summary = summarize(text, 20, &[]);
+        assert_eq!(summary, "Une seule phrase…");
+        assert!(length(&summary) <= 20);
+    }
+
+    #[test]
+    fn multibyte_character_at_byte_limit_does_not_panic() {
+        // The 120th byte falls inside “é”, where slicing the text by
+        // bytes used to panic
+        let text = format!("{}é{}", "a".repeat(119), " mot".repeat(50));
+        let summary = summarize(&text, 120, &[]);
+        assert!(length(&summary) <= 120);
+        assert!(summary.ends_with('…'));
+    }
+
+    #[test]
+    fn combining_characters_are_not_split() {
+        let text = "e\u{301}".repeat(10);
+        let summary = summarize(&text, 5, &[]);
+        assert_eq!(summary, format!("{}…", "e\u{301}".repeat(4)));
+    }
+
+    #[test]
+    fn boilerplate_lines_are_skipped() {
+        let phrases = vec!["tous droits réservés".to_string()];
+        let text = "Accueil\n© 2023 Exemple, tous droits réservés à nous.\n\
+                    Le contenu principal de la page commence ici.";
+        assert_eq!(
+            summarize(text, 280, &phrases),
+            "Le contenu principal de la page commence ici."
+        );
+    }
+
+    #[test]
+    fn boilerplate_only_text_is_summarized() {
+        let text = "Accueil\nContact";
+        assert_eq!(summarize(text, 280, &[]), "Accueil Contact");
+    }
+}
@@ -0,0 +1,167 @@
+use rocket::serde::json::{serde_json, Value};
+use tracing::info;
+
+use crate::db::models::TitleSource;
+use crate::fileparser::{
+    charset, clean_title, FileParsingError, ParsedFile, ParsingResult,
+};
+
+/// Parse a plain text file
+///
+/// The whole text is the body of the document. Its title, if any, is
+/// found later from its first line.
+pub fn parse_text(doc: &[u8], content_type: Option<&str>) -> ParsedFile {
+    info!("== Text: Parsing document");
+    ParsedFile {
+        body: charset::decode(doc, content_type).into_owned(),
+        ..Default::default()
+    }
+}
+
+/// Level and text of a Markdown heading of level 1 to 3, if `line` is
+/// one
+fn markdown_heading(line: &str) -> Option<(usize, &str)> {
+    let text = line.trim_start_matches('#');
+    let level = line.len() - text.len();
+    if (1..=3).contains(&level) && text.starts_with(' ') {
+        Some((level, text.trim().trim_end_matches('#').trim_end()))
+    } else {
+        None
+    }
+}
+
+/// Text emphasized with `**` or `__` in a line of Markdown
+fn markdown_emphasis(line: &str) -> impl Iterator<Item = &str> {
+    line.split("**")
+        .skip(1)
+        .step_by(2)
+        .chain(line.split("__").skip(1).step_by(2))
+}
+
+/// Parse a Markdown file
+///
+/// The first top-level heading is the title of the document. Headings
+/// and text in bold are extracted separately, and the whole text is
+/// the body of the document.
+pub fn parse_markdown(doc: &[u8], content_type: Option<&str>) -> ParsedFile {
+    info!("== Markdown: Parsing document");
+    let text = charset::decode(doc, content_type);
+    let headings: Vec<(usize, &str)> =
+        text.lines().filter_map(markdown_heading).collect();
+    let title = headings
+        .iter()
+        .find(|(level, _)| *level == 1)
+        .and_then(|(_, heading)| clean_title(heading))
+        .map(|title| (title, TitleSource::Heading));
+    let emphasis: Vec<&str> =
+        text.lines().flat_map(markdown_emphasis).collect();
+    ParsedFile {
+        title,
+        headings: headings
+            .iter()
+            .map(|(_, heading)| *heading)
+            .collect::<Vec<_>>()
+            .join("\n"),
+        emphasis: emphasis.join("\n"),
+        body: text.to_string(),
+        ..Default::default()
+    }
+}
+
+/// Parse a CSV file
+///
+/// The first record is considered to be the headings of the
+/// document, and all records are its body, one per line.
+///
+/// # Errors
+///
+/// If the file is not valid CSV, return a [`FileParsingError`].
+///
+/// [`FileParsingError`]: ../struct.FileParsingError.html
+pub fn parse_csv(doc: &[u8], content_type: Option<&str>) -> ParsingResult {
+    info!("== CSV: Parsing document");
+    let text = charset::decode(doc, content_type);
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(text.as_bytes());
+    let records = reader
+        .records()
+        .map(|record| {
+            record.map(|record| record.iter().collect::<Vec<_>>().join(" "))
+        })
+        .collect::<Result<Vec<String>, csv::Error>>()
+        .map_err(|e| FileParsingError(format!("Failed to parse CSV: {e}")))?;
+    Ok(ParsedFile {
+        headings: records.first().cloned().unwrap_or_default(),
+        body: records.join("\n"),
+        ..Default::default()
+    })
+}
+
+/// Collect recursively all strings held by a JSON value
+fn json_strings<'a>(value: &'a Value, strings: &mut Vec<&'a str>) {
+    match value {
+        Value::String(string) => strings.push(string),
+        Value::Array(values) => {
+            for value in values {
+                json_strings(value, strings);
+            }
+        }
+        Value::Object(map) => {
+            for value in map.values() {
+                json_strings(value, strings);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Value of the first top-level string field of `object` among `keys`
+fn json_field(object: &Value, keys: &[&str]) -> Option<String> {
+    keys.iter()
+        .find_map(|key| object.get(key).and_then(Value::as_str))
+        .map(ToString::to_string)
+}
+
+/// Parse a JSON file
+///
+/// The title, description, and keywords of the document are read
+/// from its top-level `title` or `name`, `description` or `summary`,
+/// and `keywords` or `tags` fields if it is an object. All strings
+/// held by the document are its body.
+///
+/// # Errors
+///
+/// If the file is not valid JSON, return a [`FileParsingError`].
+///
+/// [`FileParsingError`]: ../struct.FileParsingError.html
+pub fn parse_json(doc: &[u8], content_type: Option<&str>) -> ParsingResult {
+    info!("== JSON: Parsing document");
+    let text = charset::decode(doc, content_type);
+    let value: Value = serde_json::from_str(&text)
+        .map_err(|e| FileParsingError(format!("Failed to parse JSON: {e}")))?;
+    let keywords = ["keywords", "tags"]
+        .iter()
+        .find_map(|key| value.get(key))
+        .map(|keywords| {
+            let mut strings = Vec::new();
+            json_strings(keywords, &mut strings);
+            strings
+                .iter()
+                .flat_map(crate::kwparser::split_keywords)
+                .collect()
+        })
+        .unwrap_or_default();
+    let mut body = Vec::new();
+    json_strings(&value, &mut body);
+    Ok(ParsedFile {
+        title: json_field(&value, &["title", "name"])
+            .and_then(|title| clean_title(&title))
+            .map(|title| (title, TitleSource::Metadata)),
+        keywords,
+        body: body.join("\n"),
+        description: json_field(&value, &["description", "summary"]),
+        ..Default::default()
+    })
+}
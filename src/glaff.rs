@@ -0,0 +1,287 @@
+use std::fs::File;
+use std::path::Path;
+use std::sync::Arc;
+
+use color_eyre::eyre::{eyre, Result};
+use fst::Map;
+use memmap2::Mmap;
+
+use crate::normalizer::Normalizer;
+
+/// Magic number at the start of a compiled GLÀFF, including the
+/// version of its format
+pub const MAGIC: &[u8; 8] = b"GLAFFST3";
+
+/// Length of the header of a compiled GLÀFF: its magic number
+/// followed by the lengths of its FST and of its candidates, and by
+/// its folding flags, as little-endian `u64`s
+const HEADER_LENGTH: usize = MAGIC.len() + 24;
+
+/// Length of a candidate lemma in a compiled GLÀFF: the offsets of
+/// its lemma and of its GRACE tag, and its frequency
+const CANDIDATE_LENGTH: usize = 12;
+
+/// Folding flag of a compiled GLÀFF whose forms have no diacritics
+pub const DIACRITICS: u64 = 1;
+
+/// Folding flag of a compiled GLÀFF whose forms have no ligatures
+pub const LIGATURES: u64 = 1 << 1;
+
+/// Shared slice of the bytes of a compiled GLÀFF
+#[derive(Clone)]
+struct Bytes {
+    data: Arc<dyn AsRef<[u8]> + Send + Sync>,
+    start: usize,
+    end: usize,
+}
+
+impl Bytes {
+    fn new(data: Arc<dyn AsRef<[u8]> + Send + Sync>) -> Self {
+        let end = (*data).as_ref().len();
+        Self {
+            data,
+            start: 0,
+            end,
+        }
+    }
+
+    fn slice(&self, start: usize, end: usize) -> Self {
+        Self {
+            data: self.data.clone(),
+            start: self.start + start,
+            end: self.start + end,
+        }
+    }
+}
+
+impl AsRef<[u8]> for Bytes {
+    fn as_ref(&self) -> &[u8] {
+        &(*self.data).as_ref()[self.start..self.end]
+    }
+}
+
+/// Candidate lemma of an inflected form
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Candidate<'a> {
+    pub lemma: &'a str,
+    /// Morphosyntactic description of the inflected form, as a GRACE
+    /// tag such as `Ncms` for a masculine singular common noun
+    pub pos: &'a str,
+    /// Frequency of the inflected form with this lemma, in
+    /// occurrences per million words
+    pub frequency: f32,
+}
+
+impl Candidate<'_> {
+    /// Part of speech of the inflected form, the first letter of its
+    /// GRACE tag, such as `N` for nouns or `V` for verbs
+    #[must_use]
+    pub fn category(&self) -> Option<char> {
+        self.pos.chars().next()
+    }
+}
+
+/// Lemmas of inflected words, read from a compiled GLÀFF
+///
+/// A compiled GLÀFF is made of a header, an FST mapping each
+/// inflected form to the offset of its list of candidate lemmas, the
+/// lists themselves, and the strings they refer to, each followed by
+/// a NUL byte. Its file is mapped into memory rather than read, so it
+/// is loaded instantly and its pages are shared by all processes
+/// using it.
+///
+/// Forms are folded by `compile_glaff` with the normalization of the
+/// indexer, so that forms which only differ by their diacritics or
+/// ligatures share their candidate lemmas. The folding is recorded
+/// in the header, see [`Glaff::is_folded_by`].
+///
+/// [`Glaff::is_folded_by`]: ./struct.Glaff.html#method.is_folded_by
+pub struct Glaff {
+    forms: Map<Bytes>,
+    candidates: Bytes,
+    strings: Bytes,
+    /// Folding applied to the forms when the GLÀFF was compiled
+    folding: Normalizer,
+}
+
+impl Glaff {
+    /// Open a compiled GLÀFF
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be read or is not a compiled GLÀFF, return
+    /// the error to the caller.
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = File::open(path)?;
+        // SAFETY: the file is only read, and is expected not to be
+        // modified while the indexer runs.
+        let data = unsafe { Mmap::map(&file)? };
+        Self::from_bytes(&Bytes::new(Arc::new(data)))
+            .map_err(|e| eyre!("Invalid GLÀFF file {}: {}", path.display(), e))
+    }
+
+    fn from_bytes(data: &Bytes) -> Result<Self> {
+        let bytes = data.as_ref();
+        if bytes.len() < HEADER_LENGTH || &bytes[..MAGIC.len()] != MAGIC {
+            return Err(eyre!("not a compiled GLÀFF, compile it again"));
+        }
+        let length = |start: usize| {
+            let mut length = [0; 8];
+            length.copy_from_slice(&bytes[start..start + 8]);
+            usize::try_from(u64::from_le_bytes(length)).ok()
+        };
+        let forms_end = length(MAGIC.len())
+            .and_then(|length| length.checked_add(HEADER_LENGTH))
+            .filter(|&end| end <= bytes.len())
+            .ok_or_else(|| eyre!("truncated file"))?;
+        let candidates_end = length(MAGIC.len() + 8)
+            .and_then(|length| length.checked_add(forms_end))
+            .filter(|&end| end <= bytes.len())
+            .ok_or_else(|| eyre!("truncated file"))?;
+        let mut flags = [0; 8];
+        flags.copy_from_slice(&bytes[MAGIC.len() + 16..HEADER_LENGTH]);
+        let flags = u64::from_le_bytes(flags);
+        Ok(Self {
+            forms: Map::new(data.slice(HEADER_LENGTH, forms_end))?,
+            candidates: data.slice(forms_end, candidates_end),
+            strings: data.slice(candidates_end, bytes.len()),
+            folding: Normalizer {
+                nfkc: false,
+                diacritics: flags & DIACRITICS != 0,
+                ligatures: flags & LIGATURES != 0,
+            },
+        })
+    }
+
+    /// Whether the forms of the GLÀFF were folded the same way as
+    /// words folded by `normalizer`
+    ///
+    /// Otherwise, folded words may not be found among the forms of
+    /// the GLÀFF, which must be compiled again with the normalization
+    /// of the indexer.
+    #[must_use]
+    pub fn is_folded_by(&self, normalizer: Normalizer) -> bool {
+        self.folding.diacritics == normalizer.diacritics
+            && self.folding.ligatures == normalizer.ligatures
+    }
+
+    /// Read the string at `offset`
+    fn string(&self, offset: u32) -> Option<&str> {
+        let string = self.strings.as_ref().get(offset as usize..)?;
+        let end = string.iter().position(|&byte| byte == 0)?;
+        std::str::from_utf8(&string[..end]).ok()
+    }
+
+    /// Read the list of candidates at `offset`
+    fn read_candidates(&self, offset: u64) -> Option<Vec<Candidate<'_>>> {
+        let list = self
+            .candidates
+            .as_ref()
+            .get(usize::try_from(offset).ok()?..)?;
+        let count = u16::from_le_bytes([*list.first()?, *list.get(1)?]);
+        let list = list.get(2..2 + usize::from(count) * CANDIDATE_LENGTH)?;
+        list.chunks_exact(CANDIDATE_LENGTH)
+            .map(|candidate| {
+                let field = |start: usize| {
+                    let mut field = [0; 4];
+                    field.copy_from_slice(&candidate[start..start + 4]);
+                    field
+                };
+                Some(Candidate {
+                    lemma: self.string(u32::from_le_bytes(field(0)))?,
+                    pos: self.string(u32::from_le_bytes(field(4)))?,
+                    frequency: f32::from_le_bytes(field(8)),
+                })
+            })
+            .collect()
+    }
+
+    /// Get the candidate lemmas of an inflected `word`, from the most
+    /// to the least frequent
+    #[must_use]
+    pub fn lemmas(&self, word: &str) -> Vec<Candidate<'_>> {
+        self.forms
+            .get(word)
+            .and_then(|offset| self.read_candidates(offset))
+            .unwrap_or_default()
+    }
+
+    /// Get the lemma of an inflected `word`, following the word
+    /// `previous` if any
+    ///
+    /// When `word` has several lemmas, the most frequent one is
+    /// chosen, unless `previous` hints at its part of speech: nouns
+    /// and adjectives are preferred after a determiner, and verbs
+    /// after a personal pronoun. This way, `est` is lemmatized to
+    /// `être` in `il est`.
+    #[must_use]
+    pub fn get(&self, word: &str, previous: Option<&str>) -> Option<&str> {
+        let lemmas = self.lemmas(word);
+        let expected = previous.map_or(&[][..], |previous| {
+            expected_categories(&self.lemmas(previous))
+        });
+        lemmas
+            .iter()
+            .find(|lemma| {
+                matches!(lemma.category(), Some(category) if expected.contains(&category))
+            })
+            .or_else(|| lemmas.first())
+            .map(|lemma| lemma.lemma)
+    }
+}
+
+/// Parts of speech expected after a word whose candidate lemmas are
+/// `previous`
+fn expected_categories(previous: &[Candidate]) -> &'static [char] {
+    let all = |tag: &str| {
+        !previous.is_empty()
+            && previous.iter().all(|lemma| lemma.pos.starts_with(tag))
+    };
+    if all("D") {
+        &['N', 'A']
+    } else if all("Pp") {
+        &['V']
+    } else {
+        &[]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn from_bytes(bytes: Vec<u8>) -> Result<Glaff> {
+        Glaff::from_bytes(&Bytes::new(Arc::new(bytes)))
+    }
+
+    #[test]
+    fn other_formats_are_rejected() {
+        assert!(from_bytes(Vec::new()).is_err());
+        let mut bytes = b"GLAFFST2".to_vec();
+        bytes.resize(HEADER_LENGTH, 0);
+        assert!(from_bytes(bytes).is_err());
+    }
+
+    #[test]
+    fn truncated_files_are_rejected() {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&1024_u64.to_le_bytes());
+        bytes.extend_from_slice(&0_u64.to_le_bytes());
+        bytes.extend_from_slice(&0_u64.to_le_bytes());
+        assert!(from_bytes(bytes).is_err());
+    }
+
+    #[test]
+    fn folding_flags_are_read() {
+        let forms = Map::default().into_fst().into_inner();
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&(forms.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(&0_u64.to_le_bytes());
+        bytes.extend_from_slice(&LIGATURES.to_le_bytes());
+        bytes.extend_from_slice(&forms);
+        let glaff = from_bytes(bytes).unwrap();
+        assert!(glaff.is_folded_by("ligatures".parse().unwrap()));
+        assert!(!glaff.is_folded_by("ligatures,diacritics".parse().unwrap()));
+        assert!(glaff.lemmas("oeuvre").is_empty());
+    }
+}
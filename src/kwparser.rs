@@ -1,8 +1,10 @@
-use std::collections::HashMap;
 use std::fs::read_to_string;
 use std::path::PathBuf;
 
-use rayon::prelude::*;
+use color_eyre::eyre::Result;
+use rust_stemmers::Stemmer;
+
+use crate::glaff::Glaff;
 
 /// Get list of stopwords from a file.
 ///
@@ -15,47 +17,72 @@ use rayon::prelude::*;
 #[must_use]
 pub fn get_stopwords(path: PathBuf) -> Vec<String> {
     let content = read_to_string(path).unwrap();
-    let words: Vec<String> = content
-        .split('\n')
-        .map(std::string::ToString::to_string)
-        .collect();
-    words
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|word| !word.is_empty())
+        .map(ToString::to_string)
+        .collect()
 }
 
-pub type Glaff = HashMap<String, String>;
-
-/// Read the GLÀFF in its binary version
+/// Read the compiled GLÀFF, see [`Glaff`]
 ///
-/// # Panics
+/// If `path` is `None`, return nothing.
 ///
-/// The program may panic if `path` is not readable or if the
-/// deserialization fails.
-#[must_use]
-pub fn read_glaff(path: Option<PathBuf>) -> Option<Glaff> {
-    match path {
-        None => None,
-        Some(p) => {
-            let data = std::fs::read(p).unwrap();
-            Some(bincode::deserialize(&data).unwrap())
-        }
-    }
+/// # Errors
+///
+/// If the file cannot be read or is not a compiled GLÀFF, return the
+/// error to the caller.
+///
+/// [`Glaff`]: ../glaff/struct.Glaff.html
+pub fn read_glaff(path: Option<PathBuf>) -> Result<Option<Glaff>> {
+    path.map(|path| Glaff::open(&path)).transpose()
 }
 
-/// Get a lemma from the GLAFF
+/// Get the lemma of a word
+///
+/// The lemma of `word` is looked up in the GLÀFF, where the word
+/// `previous` may help choosing among several lemmas (see
+/// [`Glaff::get`]). If it cannot be found there, or if no GLÀFF is
+/// loaded, `word` is stemmed with `stemmer` instead so its
+/// morphological variants still share a common form. Without a
+/// stemmer, `word` is returned as is.
+///
+/// [`Glaff::get`]: ../glaff/struct.Glaff.html#method.get
 #[must_use]
-pub fn get_lemma_from_glaff(word: String, glaff: &Option<Glaff>) -> String {
-    match glaff {
+pub fn get_lemma(
+    word: String,
+    previous: Option<&str>,
+    glaff: &Option<Glaff>,
+    stemmer: Option<&Stemmer>,
+) -> String {
+    if let Some(lemma) =
+        glaff.as_ref().and_then(|glaff| glaff.get(&word, previous))
+    {
+        return lemma.to_string();
+    }
+    match stemmer {
+        Some(stemmer) => stemmer.stem(&word).into_owned(),
         None => word,
-        Some(collection) => match collection.get(&word) {
-            Some(lemme) => lemme.clone(),
-            None => word,
-        },
     }
 }
 
-/// Determine if a word is a stopword
-fn is_stopword(word: &String, stop_words: &[String]) -> bool {
-    stop_words.contains(word)
+/// Keyword extracted from a text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Term {
+    /// Normalized form of the keyword, under which it is indexed and
+    /// searched
+    pub word: String,
+    /// Form of the keyword as found in the text, in lowercase, kept
+    /// for display
+    pub form: String,
+    /// Offset in bytes of the keyword in the text
+    pub offset: usize,
+    /// Position of the keyword in the text, counted in words, see
+    /// [`Token::position`]
+    ///
+    /// [`Token::position`]: ../tokenizer/struct.Token.html#structfield.position
+    pub position: usize,
 }
 
 pub fn split_keywords<T>(keywords: &T) -> Vec<String>
@@ -69,35 +96,3 @@ where
         .map(std::string::ToString::to_string)
         .collect()
 }
-
-/// Determine if a word is a short word
-///
-/// # Examples
-///
-/// ```
-/// assert!(is_short_word("je"));
-/// assert!(!(is_short_word("bonjour")));
-/// ```
-fn is_short_word(word: &String) -> bool {
-    word.len() <= 2
-}
-
-#[must_use]
-#[allow(clippy::implicit_hasher)]
-pub fn get_keywords_from_text(
-    text: &str,
-    stop_words: &[String],
-    lemmes: &Option<Glaff>,
-) -> Vec<String> {
-    text.split(|c| !char::is_alphabetic(c))
-        .par_bridge()
-        .filter_map(|e| {
-            let word = get_lemma_from_glaff(e.to_lowercase(), lemmes);
-            if !is_short_word(&word) && !is_stopword(&word, stop_words) {
-                Some(word)
-            } else {
-                None
-            }
-        })
-        .collect::<Vec<String>>()
-}
@@ -0,0 +1,574 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, PoisonError, RwLock};
+
+use color_eyre::eyre::{eyre, Result};
+use rust_stemmers::{Algorithm, Stemmer};
+use tracing::{debug, info};
+use whatlang::{Detector, Info, Lang};
+
+use crate::analyzer::Analyzer;
+use crate::kwparser;
+use crate::normalizer::Normalizer;
+use crate::spelling::{self, Dictionary};
+use crate::tokenizer::Tokenizer;
+
+/// ISO 639-1 codes of the languages which can be detected from text
+const LANGUAGES: [(&str, Lang); 69] = [
+    ("af", Lang::Afr),
+    ("ak", Lang::Aka),
+    ("am", Lang::Amh),
+    ("ar", Lang::Ara),
+    ("az", Lang::Aze),
+    ("be", Lang::Bel),
+    ("bg", Lang::Bul),
+    ("bn", Lang::Ben),
+    ("ca", Lang::Cat),
+    ("cs", Lang::Ces),
+    ("da", Lang::Dan),
+    ("de", Lang::Deu),
+    ("el", Lang::Ell),
+    ("en", Lang::Eng),
+    ("eo", Lang::Epo),
+    ("es", Lang::Spa),
+    ("et", Lang::Est),
+    ("fa", Lang::Pes),
+    ("fi", Lang::Fin),
+    ("fr", Lang::Fra),
+    ("gu", Lang::Guj),
+    ("he", Lang::Heb),
+    ("hi", Lang::Hin),
+    ("hr", Lang::Hrv),
+    ("hu", Lang::Hun),
+    ("hy", Lang::Hye),
+    ("id", Lang::Ind),
+    ("it", Lang::Ita),
+    ("ja", Lang::Jpn),
+    ("jv", Lang::Jav),
+    ("ka", Lang::Kat),
+    ("km", Lang::Khm),
+    ("kn", Lang::Kan),
+    ("ko", Lang::Kor),
+    ("la", Lang::Lat),
+    ("lt", Lang::Lit),
+    ("lv", Lang::Lav),
+    ("mk", Lang::Mkd),
+    ("ml", Lang::Mal),
+    ("mr", Lang::Mar),
+    ("my", Lang::Mya),
+    ("nb", Lang::Nob),
+    ("ne", Lang::Nep),
+    ("nl", Lang::Nld),
+    ("or", Lang::Ori),
+    ("pa", Lang::Pan),
+    ("pl", Lang::Pol),
+    ("pt", Lang::Por),
+    ("ro", Lang::Ron),
+    ("ru", Lang::Rus),
+    ("si", Lang::Sin),
+    ("sk", Lang::Slk),
+    ("sl", Lang::Slv),
+    ("sn", Lang::Sna),
+    ("sr", Lang::Srp),
+    ("sv", Lang::Swe),
+    ("ta", Lang::Tam),
+    ("te", Lang::Tel),
+    ("th", Lang::Tha),
+    ("tk", Lang::Tuk),
+    ("tl", Lang::Tgl),
+    ("tr", Lang::Tur),
+    ("uk", Lang::Ukr),
+    ("ur", Lang::Urd),
+    ("uz", Lang::Uzb),
+    ("vi", Lang::Vie),
+    ("yi", Lang::Yid),
+    ("zh", Lang::Cmn),
+    ("zu", Lang::Zul),
+];
+
+/// Amount of characters of a text read to detect its language
+const DETECTION_LENGTH: usize = 4096;
+
+/// Minimum confidence of the language detected from a query
+///
+/// Queries are too short for their language to be reliably detected,
+/// but their language is only chosen among the languages with a
+/// pipeline.
+const MIN_QUERY_CONFIDENCE: f64 = 0.1;
+
+fn to_lang(code: &str) -> Option<Lang> {
+    LANGUAGES
+        .iter()
+        .find(|(language, _)| *language == code)
+        .map(|(_, lang)| *lang)
+}
+
+fn from_lang(lang: Lang) -> Option<&'static str> {
+    LANGUAGES
+        .iter()
+        .find(|(_, language)| *language == lang)
+        .map(|(code, _)| *code)
+}
+
+/// Snowball stemmer of a language, if it has one
+#[must_use]
+pub fn stemmer(language: &str) -> Option<Stemmer> {
+    let algorithm = match language {
+        "ar" => Algorithm::Arabic,
+        "da" => Algorithm::Danish,
+        "nl" => Algorithm::Dutch,
+        "en" => Algorithm::English,
+        "fi" => Algorithm::Finnish,
+        "fr" => Algorithm::French,
+        "de" => Algorithm::German,
+        "el" => Algorithm::Greek,
+        "hu" => Algorithm::Hungarian,
+        "it" => Algorithm::Italian,
+        "nb" | "no" => Algorithm::Norwegian,
+        "pt" => Algorithm::Portuguese,
+        "ro" => Algorithm::Romanian,
+        "ru" => Algorithm::Russian,
+        "es" => Algorithm::Spanish,
+        "sv" => Algorithm::Swedish,
+        "ta" => Algorithm::Tamil,
+        "tr" => Algorithm::Turkish,
+        _ => return None,
+    };
+    Some(Stemmer::create(algorithm))
+}
+
+/// Normalize a language tag
+///
+/// Only the primary subtag of the language tag is kept, in lowercase.
+/// For instance, `fr-FR` becomes `fr`. Return `None` if the tag is
+/// not a valid language tag.
+#[must_use]
+pub fn normalize_tag(tag: &str) -> Option<String> {
+    let primary = tag.trim().split(['-', '_']).next()?;
+    if (2..=3).contains(&primary.len())
+        && primary.chars().all(|c| c.is_ascii_alphabetic())
+    {
+        Some(primary.to_ascii_lowercase())
+    } else {
+        None
+    }
+}
+
+/// Detect the language of `text` among all known languages
+///
+/// Return the ISO 639-1 code of the language, or `None` if it cannot
+/// be reliably detected.
+#[must_use]
+pub fn detect(text: &str) -> Option<String> {
+    detect_with(&Detector::new(), text)
+        .filter(Info::is_reliable)
+        .and_then(|info| from_lang(info.lang()))
+        .map(ToString::to_string)
+}
+
+fn detect_with(detector: &Detector, text: &str) -> Option<Info> {
+    let end = text
+        .char_indices()
+        .nth(DETECTION_LENGTH)
+        .map_or(text.len(), |(index, _)| index);
+    let info = detector.detect(&text[..end])?;
+    debug!(
+        "====== Detected language {} with confidence {}",
+        info.lang(),
+        info.confidence()
+    );
+    Some(info)
+}
+
+/// Resources used to analyse text in a given language
+#[derive(Default)]
+pub struct Pipeline {
+    /// Analyzer of documents and queries
+    pub analyzer: Analyzer,
+    /// Lowercase snippets of text commonly found in boilerplate
+    /// lines, left out of generated summaries, see
+    /// [`summary::summarize`]
+    ///
+    /// [`summary::summarize`]: ../fileparser/summary/fn.summarize.html
+    pub boilerplate: Vec<String>,
+    /// Dictionary trained on a corpus with `spelling_trainer`
+    corpus: Option<Arc<Dictionary>>,
+    /// Dictionary used to correct the spelling of queries, which may
+    /// be trained again while the server runs, see
+    /// [`Pipeline::train_dictionary`]
+    ///
+    /// [`Pipeline::train_dictionary`]: ./struct.Pipeline.html#method.train_dictionary
+    dictionary: RwLock<Option<Arc<Dictionary>>>,
+}
+
+impl Pipeline {
+    /// Create a pipeline analysing text with `analyzer` and correcting
+    /// its spelling with the corpus `dictionary`
+    #[must_use]
+    pub fn new(analyzer: Analyzer, dictionary: Option<Dictionary>) -> Self {
+        let dictionary = dictionary.map(Arc::new);
+        Self {
+            analyzer,
+            boilerplate: Vec::new(),
+            corpus: dictionary.clone(),
+            dictionary: RwLock::new(dictionary),
+        }
+    }
+
+    /// Leave lines containing one of the boilerplate `phrases` out of
+    /// generated summaries
+    #[must_use]
+    pub fn with_boilerplate(mut self, phrases: &[String]) -> Self {
+        self.boilerplate =
+            phrases.iter().map(|phrase| phrase.to_lowercase()).collect();
+        self
+    }
+
+    /// Dictionary used to correct the spelling of queries
+    #[must_use]
+    pub fn dictionary(&self) -> Option<Arc<Dictionary>> {
+        self.dictionary
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Train the dictionary on `words` and their number of
+    /// occurrences, such as the vocabulary of indexed documents
+    ///
+    /// `words` are added to the words of the corpus dictionary if
+    /// any, whose pairs of words are kept. The new dictionary then
+    /// replaces the previous one at once.
+    #[allow(clippy::implicit_hasher)]
+    pub fn train_dictionary(&self, mut words: HashMap<String, usize>) {
+        let bigrams = match &self.corpus {
+            Some(corpus) => {
+                for (word, occurrences) in &corpus.words {
+                    *words.entry(word.clone()).or_default() += occurrences;
+                }
+                corpus.bigrams.clone()
+            }
+            None => HashMap::new(),
+        };
+        let dictionary = Dictionary::new(words, bigrams);
+        *self
+            .dictionary
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) =
+            Some(Arc::new(dictionary));
+    }
+
+    /// Load a pipeline from a directory
+    ///
+    /// The directory may contain a list of stop words in
+    /// `stopwords.txt`, a list of boilerplate phrases in
+    /// `boilerplate.txt`, a lemma table in the format of the compiled
+    /// GLÀFF in `lemmas.bin`, and a dictionary in `dictionary.bin`.
+    /// Missing files are ignored.
+    ///
+    /// # Errors
+    ///
+    /// If the lemma table or the dictionary cannot be read, return
+    /// the error to the caller.
+    pub fn from_dir(dir: &Path) -> Result<Self> {
+        let stopwords = dir.join("stopwords.txt");
+        let boilerplate = dir.join("boilerplate.txt");
+        let glaff = dir.join("lemmas.bin");
+        let dictionary = dir.join("dictionary.bin");
+        let stopwords = if stopwords.is_file() {
+            kwparser::get_stopwords(stopwords)
+        } else {
+            Vec::new()
+        };
+        let boilerplate = if boilerplate.is_file() {
+            kwparser::get_stopwords(boilerplate)
+        } else {
+            Vec::new()
+        };
+        Ok(Self::new(
+            Analyzer::default().with_stopwords(&stopwords).with_glaff(
+                kwparser::read_glaff(glaff.is_file().then_some(glaff))?,
+            ),
+            spelling::read_dictionary(
+                dictionary.is_file().then_some(dictionary),
+            )?,
+        )
+        .with_boilerplate(&boilerplate))
+    }
+}
+
+/// Analysis pipelines of all languages supported by the indexer
+///
+/// Text whose language has no pipeline, or whose language is unknown,
+/// is analysed with the pipeline of the default language.
+pub struct Languages {
+    default: String,
+    pipelines: HashMap<String, Pipeline>,
+    detector: Detector,
+}
+
+impl Languages {
+    /// Create a new set of languages with only the `default` language
+    ///
+    /// # Errors
+    ///
+    /// If `default` is not a valid language tag, see
+    /// [`normalize_tag`], return an error to the caller.
+    ///
+    /// [`normalize_tag`]: ./fn.normalize_tag.html
+    pub fn new(default: &str, mut pipeline: Pipeline) -> Result<Self> {
+        let default = normalize_tag(default)
+            .ok_or_else(|| eyre!("Invalid language tag {default:?}"))?;
+        pipeline.analyzer.tokenizer = Tokenizer::for_language(
+            &default,
+            pipeline.analyzer.tokenizer.min_length,
+        );
+        let mut languages = Self {
+            default,
+            pipelines: HashMap::new(),
+            detector: Detector::new(),
+        };
+        languages
+            .pipelines
+            .insert(languages.default.clone(), pipeline);
+        Ok(languages.with_detector())
+    }
+
+    /// Add the pipeline of `language`
+    ///
+    /// The tokenizer of the pipeline is replaced by the one suited to
+    /// `language`, see [`Tokenizer::for_language`].
+    ///
+    /// [`Tokenizer::for_language`]: ../tokenizer/struct.Tokenizer.html#method.for_language
+    #[must_use]
+    pub fn with_pipeline(
+        mut self,
+        language: &str,
+        mut pipeline: Pipeline,
+    ) -> Self {
+        if let Some(language) = normalize_tag(language) {
+            info!("== Adding pipeline for language {language}");
+            pipeline.analyzer.tokenizer = Tokenizer::for_language(
+                &language,
+                pipeline.analyzer.tokenizer.min_length,
+            );
+            self.pipelines.insert(language, pipeline);
+        }
+        self.with_detector()
+    }
+
+    /// Add the pipelines found in `dir`
+    ///
+    /// Each subdirectory of `dir` named after the ISO 639-1 code of a
+    /// language holds its pipeline, see [`Pipeline::from_dir`].
+    ///
+    /// # Errors
+    ///
+    /// If `dir` or a pipeline cannot be read, return the error to the
+    /// caller.
+    ///
+    /// [`Pipeline::from_dir`]: ./struct.Pipeline.html#method.from_dir
+    pub fn with_dir(mut self, dir: &Path) -> Result<Self> {
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
+                if path.is_dir() {
+                    let pipeline = Pipeline::from_dir(&path)?;
+                    self = self.with_pipeline(name, pipeline);
+                }
+            }
+        }
+        Ok(self)
+    }
+
+    /// Stem words without a lemma in all languages
+    ///
+    /// Each language with a Snowball stemmer, see [`stemmer`], uses it
+    /// for words missing from its lemmas.
+    ///
+    /// [`stemmer`]: ./fn.stemmer.html
+    #[must_use]
+    pub fn with_stemming(mut self) -> Self {
+        for (language, pipeline) in &mut self.pipelines {
+            pipeline.analyzer.stemmer = stemmer(language);
+            if pipeline.analyzer.stemmer.is_none() {
+                info!("== No stemmer available for language {language}");
+            }
+        }
+        self
+    }
+
+    /// Normalize words with `normalizer` in all languages, see
+    /// [`Analyzer::with_normalizer`]
+    ///
+    /// # Errors
+    ///
+    /// If the GLÀFF of a language was compiled with another
+    /// normalization, see [`Glaff::is_folded_by`], return an error to
+    /// the caller.
+    ///
+    /// [`Analyzer::with_normalizer`]: ../analyzer/struct.Analyzer.html#method.with_normalizer
+    /// [`Glaff::is_folded_by`]: ../glaff/struct.Glaff.html#method.is_folded_by
+    pub fn with_normalizer(mut self, normalizer: Normalizer) -> Result<Self> {
+        for (language, pipeline) in &mut self.pipelines {
+            if let Some(glaff) = &pipeline.analyzer.glaff {
+                if !glaff.is_folded_by(normalizer) {
+                    return Err(eyre!(
+                        "The GLÀFF of {language} was compiled with another \
+                         normalization, compile it again with the same \
+                         --normalization as the indexer"
+                    ));
+                }
+            }
+            pipeline.analyzer = std::mem::take(&mut pipeline.analyzer)
+                .with_normalizer(normalizer);
+        }
+        Ok(self)
+    }
+
+    /// Ignore words shorter than `length` characters in all languages
+    #[must_use]
+    pub fn with_min_word_length(mut self, length: usize) -> Self {
+        for pipeline in self.pipelines.values_mut() {
+            pipeline.analyzer.tokenizer.min_length = length;
+        }
+        self
+    }
+
+    /// Restrict the detection of queries’ language to languages with
+    /// a pipeline
+    fn with_detector(mut self) -> Self {
+        let allowed = self
+            .pipelines
+            .keys()
+            .filter_map(|code| to_lang(code))
+            .collect::<Vec<_>>();
+        self.detector = Detector::with_allowlist(allowed);
+        self
+    }
+
+    /// ISO 639-1 code of the default language
+    #[must_use]
+    pub fn default_language(&self) -> &str {
+        &self.default
+    }
+
+    /// Whether `language` has a pipeline of its own
+    #[must_use]
+    pub fn has_pipeline(&self, language: &str) -> bool {
+        self.pipelines.contains_key(language)
+    }
+
+    /// Languages with a pipeline, along with their pipeline
+    pub fn pipelines(&self) -> impl Iterator<Item = (&str, &Pipeline)> {
+        self.pipelines
+            .iter()
+            .map(|(language, pipeline)| (language.as_str(), pipeline))
+    }
+
+    /// Language whose pipeline analyses text in `language`, that is
+    /// `language` itself if it has a pipeline, or the default language
+    #[must_use]
+    pub fn resolve(&self, language: Option<&str>) -> &str {
+        language
+            .and_then(normalize_tag)
+            .and_then(|language| self.pipelines.get_key_value(&language))
+            .map_or(&self.default, |(language, _)| language)
+    }
+
+    /// Pipeline of `language`, or of the default language if it has
+    /// none
+    #[must_use]
+    pub fn pipeline(&self, language: Option<&str>) -> &Pipeline {
+        &self.pipelines[self.resolve(language)]
+    }
+
+    /// Replace the stop words of all languages
+    ///
+    /// `stopwords` holds pairs of languages and words. Languages
+    /// without any stop word in `stopwords` are left without stop
+    /// words. See [`Analyzer::set_stopwords`].
+    ///
+    /// [`Analyzer::set_stopwords`]: ../analyzer/struct.Analyzer.html#method.set_stopwords
+    pub fn set_stopwords(&self, stopwords: &[(String, String)]) {
+        for (language, pipeline) in &self.pipelines {
+            let words = stopwords
+                .iter()
+                .filter(|(l, _)| l == language)
+                .map(|(_, word)| word.clone())
+                .collect::<Vec<String>>();
+            debug!("== {} stop words for language {language}", words.len());
+            pipeline.analyzer.set_stopwords(&words);
+        }
+    }
+
+    /// Train the dictionaries of all languages on the vocabulary of
+    /// indexed documents, see [`Pipeline::train_dictionary`]
+    ///
+    /// `vocabulary` holds triples of the language of a document, the
+    /// form of a word, and its number of occurrences, see
+    /// [`db::vocabulary`]. Only forms made of letters are kept. Return
+    /// the number of words of the dictionary of each language.
+    ///
+    /// [`Pipeline::train_dictionary`]: ./struct.Pipeline.html#method.train_dictionary
+    /// [`db::vocabulary`]: ../db/fn.vocabulary.html
+    #[must_use]
+    pub fn train_dictionaries(
+        &self,
+        vocabulary: Vec<(Option<String>, String, i64)>,
+    ) -> HashMap<String, usize> {
+        let mut words: HashMap<&str, HashMap<String, usize>> = self
+            .pipelines
+            .keys()
+            .map(|language| (language.as_str(), HashMap::new()))
+            .collect();
+        for (language, form, occurrences) in vocabulary {
+            if form.is_empty() || !form.chars().all(char::is_alphabetic) {
+                continue;
+            }
+            let language = self.resolve(language.as_deref());
+            if let Some(words) = words.get_mut(language) {
+                *words.entry(form).or_default() +=
+                    usize::try_from(occurrences).unwrap_or_default();
+            }
+        }
+        words
+            .into_iter()
+            .map(|(language, words)| {
+                let pipeline = &self.pipelines[language];
+                pipeline.train_dictionary(words);
+                let size = pipeline.dictionary().map_or(0, |d| d.words.len());
+                info!("== Trained dictionary of {language} on {size} words");
+                (language.to_string(), size)
+            })
+            .collect()
+    }
+
+    /// Find the language of a query
+    ///
+    /// The language `requested` by the user is preferred. Otherwise,
+    /// it is detected among the languages with a pipeline, or it is
+    /// the default language if the detection is not confident enough.
+    #[must_use]
+    pub fn query_language(
+        &self,
+        requested: Option<&str>,
+        query: &str,
+    ) -> String {
+        requested
+            .and_then(normalize_tag)
+            .or_else(|| {
+                if self.pipelines.len() > 1 {
+                    detect_with(&self.detector, query)
+                        .filter(|info| {
+                            info.confidence() >= MIN_QUERY_CONFIDENCE
+                        })
+                        .and_then(|info| from_lang(info.lang()))
+                        .map(ToString::to_string)
+                } else {
+                    None
+                }
+            })
+            .unwrap_or_else(|| self.default.clone())
+    }
+}
@@ -7,11 +7,17 @@ extern crate rocket;
 use tracing::Level;
 use tracing_subscriber::FmtSubscriber;
 
-pub mod spelling;
-pub mod server;
+pub mod analyzer;
 pub mod db;
-pub mod kwparser;
 pub mod fileparser;
+pub mod glaff;
+pub mod kwparser;
+pub mod language;
+pub mod normalizer;
+pub mod server;
+pub mod spelling;
+pub mod synonyms;
+pub mod tokenizer;
 
 pub fn setup_logging() {
     let subscriber = FmtSubscriber::builder()
@@ -4,20 +4,27 @@
 #[macro_use]
 extern crate rocket;
 
-use color_eyre::eyre::Result;
-use tracing::info;
+use color_eyre::eyre::{eyre, Result};
+use tracing::{info, warn};
 
 use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
 use structopt::StructOpt;
 
 use rocket::http::Method;
 use rocket_cors::{AllOrSome, AllowedHeaders, AllowedOrigins, Cors, Origins};
 
+mod analyzer;
 mod db;
 mod fileparser;
+mod glaff;
 mod kwparser;
+mod language;
+mod normalizer;
 mod server;
 mod spelling;
+mod synonyms;
+mod tokenizer;
 
 macro_rules! from_env {
     ($name:literal) => {
@@ -28,17 +35,102 @@ macro_rules! from_env {
 #[derive(StructOpt, Debug)]
 #[structopt(name = "indexer")]
 struct Opt {
-    /// Path to a list of stop words to ignore
+    /// Path to a list of stop words to ignore in the default language,
+    /// imported into the database on startup
     #[structopt(short = "s", long, parse(from_os_str))]
     stop_words: PathBuf,
 
-    /// Path to the binary version of the GLÀFF (optional)
+    /// Path to a list of phrases found in boilerplate lines of the
+    /// default language, left out of generated descriptions (optional)
+    #[structopt(short = "b", long, parse(from_os_str))]
+    boilerplate: Option<PathBuf>,
+
+    /// Path to the binary version of the GLÀFF, used in the default
+    /// language (optional)
     #[structopt(short = "g", long, parse(from_os_str))]
     glaff: Option<PathBuf>,
 
-    /// Path to the binary version of the dictionary (optional)
+    /// Path to the binary version of the dictionary of the default
+    /// language (optional)
     #[structopt(short = "d", long, parse(from_os_str))]
     dictionary: Option<PathBuf>,
+
+    /// ISO 639-1 code of the default language, used when the language
+    /// of a document or a query is unknown
+    #[structopt(long, default_value = "fr")]
+    language: String,
+
+    /// Directory holding the stop words, boilerplate phrases, lemmas,
+    /// and dictionary of other languages, in subdirectories named
+    /// after their ISO 639-1 code (optional)
+    #[structopt(long, parse(from_os_str))]
+    languages_dir: Option<PathBuf>,
+
+    /// Stem words without a lemma with the Snowball stemmer of their
+    /// language, if it has one
+    #[structopt(long)]
+    stemming: bool,
+
+    /// Normalization applied to words, as a comma-separated list of
+    /// steps among nfkc, diacritics, and ligatures, or none
+    #[structopt(long, default_value = "nfkc")]
+    normalization: normalizer::Normalizer,
+
+    /// Minimum length in characters of indexed words. Numbers and
+    /// tokens mixing letters, digits, or symbols are always indexed.
+    #[structopt(long, default_value = "3")]
+    min_word_length: usize,
+
+    /// Maximum length in characters of descriptions generated for
+    /// documents without one
+    #[structopt(long, default_value = "280")]
+    description_length: usize,
+
+    /// Weight of words depending on the field they are found in, as
+    /// comma-separated field=weight pairs. Fields are title,
+    /// description, heading, emphasis, body, keywords, and boilerplate.
+    /// Unlisted fields keep their default weight, zero ignores a field
+    /// (optional)
+    #[structopt(long)]
+    weights: Option<db::weights::FieldWeights>,
+
+    /// Weight in percent of the synonyms of the words of a query,
+    /// relative to the words themselves
+    #[structopt(long, default_value = "50")]
+    synonym_weight: i32,
+
+    /// Probability above which a spelling suggestion is offered for a
+    /// query, between 0 and 1
+    #[structopt(long, default_value = "0.5")]
+    spelling_confidence: f64,
+
+    /// Train the spelling dictionary of each language on the words of
+    /// indexed documents on startup, on top of its corpus dictionary
+    #[structopt(long)]
+    train_dictionary: bool,
+
+    /// Where to store uploaded documents: s3, local, or memory
+    #[structopt(long, default_value = "s3")]
+    storage: server::storage::Backend,
+
+    /// Directory in which documents are stored with the local storage
+    #[structopt(long, parse(from_os_str), default_value = "files")]
+    storage_path: PathBuf,
+
+    /// Public URL of the indexer, used to download documents stored
+    /// without a public URL
+    #[structopt(long, default_value = "http://localhost:8000")]
+    public_url: String,
+
+    /// Lifetime in seconds of the signed links through which documents
+    /// stored without a public URL are downloaded
+    #[structopt(long, default_value = "3600")]
+    download_expiry: u64,
+
+    /// Lifetime in seconds of presigned download URLs for documents
+    /// stored in a S3 bucket, which can then stay private (optional)
+    #[structopt(long)]
+    presign_expiry: Option<u32>,
 }
 
 fn make_cors(
@@ -46,10 +138,15 @@ fn make_cors(
 ) -> Result<Cors, rocket_cors::Error> {
     rocket_cors::CorsOptions {
         allowed_origins,
-        allowed_methods: vec![Method::Get, Method::Post, Method::Delete]
-            .into_iter()
-            .map(From::from)
-            .collect(),
+        allowed_methods: vec![
+            Method::Get,
+            Method::Post,
+            Method::Patch,
+            Method::Delete,
+        ]
+        .into_iter()
+        .map(From::from)
+        .collect(),
         allowed_headers: AllowedHeaders::some(&[
             "Authorization",
             "Accept",
@@ -62,6 +159,47 @@ fn make_cors(
     .to_cors()
 }
 
+/// Read the analysis pipelines of the default language and of the
+/// languages found in the languages directory
+///
+/// # Errors
+///
+/// If the default language is not a valid language tag, or if a lemma
+/// table, a dictionary, or the languages directory cannot be read,
+/// return the error to the caller.
+fn read_languages(opt: &Opt) -> Result<language::Languages> {
+    info!("Reading stopwords");
+    let stopwords = kwparser::get_stopwords(opt.stop_words.clone());
+    let boilerplate = opt
+        .boilerplate
+        .clone()
+        .map(kwparser::get_stopwords)
+        .unwrap_or_default();
+    info!("Reading GLÀFF");
+    let glaff = kwparser::read_glaff(opt.glaff.clone())?;
+    info!("Reading dictionary");
+    let dictionary = spelling::read_dictionary(opt.dictionary.clone())?;
+    let mut languages = language::Languages::new(
+        &opt.language,
+        language::Pipeline::new(
+            analyzer::Analyzer::default()
+                .with_stopwords(&stopwords)
+                .with_glaff(glaff),
+            dictionary,
+        )
+        .with_boilerplate(&boilerplate),
+    )?;
+    if let Some(dir) = &opt.languages_dir {
+        info!("Reading languages from {}", dir.display());
+        languages = languages.with_dir(dir)?;
+    }
+    if opt.stemming {
+        info!("Enabling stemming");
+        languages = languages.with_stemming();
+    }
+    Ok(languages.with_min_word_length(opt.min_word_length))
+}
+
 #[rocket::main]
 async fn main() -> Result<()> {
     color_eyre::install()?;
@@ -69,25 +207,47 @@ async fn main() -> Result<()> {
 
     let opt = Opt::from_args();
 
-    info!("Reading stopwords");
-    let stopwords = kwparser::get_stopwords(opt.stop_words);
-    info!("Reading GLÀFF");
-    let glaff = kwparser::read_glaff(opt.glaff);
-    info!("Reading dictionary");
-    let dictionary = spelling::read_dictionary(opt.dictionary)?;
+    let languages = read_languages(&opt)?;
 
     let allowed_origins = AllowedOrigins::some_regex(&[".*"]);
     let cors = make_cors(allowed_origins)?;
     let pool = db::get_connection_pool();
-    let s3_bucket = server::s3::connect_to_bucket(
-        from_env!("S3_BUCKET_ID").as_str(),
-        from_env!("S3_REGION"),
-        from_env!("S3_ENDPOINT"),
-    )?;
+    info!("Setting up {:?} storage", opt.storage);
+    let storage = server::storage::make_storage(
+        opt.storage,
+        opt.storage_path,
+        opt.presign_expiry,
+    )
+    .map_err(|e| eyre!(e.to_string()))?;
 
     info!("Running database migrations");
     db::run_migrations(&mut pool.get()?)?;
 
+    info!("Loading stopwords");
+    for (language, pipeline) in languages.pipelines() {
+        let stopwords = pipeline.analyzer.stopwords();
+        db::import_stopwords(&mut *pool.get()?, language, &stopwords)?;
+    }
+    let languages = languages.with_normalizer(opt.normalization)?;
+    languages.set_stopwords(&db::list_stopwords(&mut *pool.get()?)?);
+
+    if opt.train_dictionary {
+        info!("Training dictionaries on indexed documents");
+        match db::vocabulary(&mut *pool.get()?) {
+            Ok(vocabulary) => {
+                let sizes = languages.train_dictionaries(vocabulary);
+                info!("Trained {} dictionaries", sizes.len());
+            }
+            Err(e) => {
+                warn!("Failed to train dictionaries: {e}");
+            }
+        }
+    }
+
+    info!("Loading synonyms");
+    let mut synonyms = synonyms::Synonyms::new(opt.synonym_weight);
+    synonyms.load(&db::list_synonyms(&mut *pool.get()?)?, &languages);
+
     info!("Launching server");
     #[allow(clippy::let_underscore_drop)]
     let _ = rocket::build()
@@ -98,18 +258,35 @@ async fn main() -> Result<()> {
                 server::index_upload, // POST   /docs/file/:filename + binary file + AUTH
                 server::index_url,    // POST   /docs/url/:url + AUTH
                 server::delete_document, // DELETE /docs/:id + AUTH
+                server::update_document, // PATCH  /docs/:id + AUTH
                 server::document_list_keywords, // GET    /docs/:id/keywords
-                server::search_query, // GET    /search/:query
-                server::spelling_word, // GET    /spelling/:word
+                server::download_document, // GET    /docs/:id/download + signature
+                server::search_query,      // GET    /search/:query
+                server::spelling_word,     // GET    /spelling/:word
+                server::train_spelling,    // POST   /spelling/train + AUTH
+                server::list_synonyms,     // GET    /synonyms + AUTH
+                server::add_synonyms,      // POST   /synonyms + AUTH
+                server::update_synonyms,   // PATCH  /synonyms/:id + AUTH
+                server::delete_synonyms,   // DELETE /synonyms/:id + AUTH
+                server::reload_synonyms,   // POST   /synonyms/reload + AUTH
+                server::list_stopwords,    // GET    /stopwords/:lang + AUTH
+                server::add_stopwords,     // POST   /stopwords/:lang + AUTH
+                server::remove_stopwords,  // DELETE /stopwords/:lang + AUTH
             ],
         )
         .attach(cors)
         .manage(server::ServerState {
-            dictionary,
-            glaff,
+            languages: Arc::new(languages),
             pool,
-            stopwords,
-            s3_bucket,
+            description_length: opt.description_length,
+            weights: opt.weights.unwrap_or_default(),
+            synonyms: RwLock::new(synonyms),
+            spelling_confidence: opt.spelling_confidence,
+            storage: Arc::from(storage),
+            links: server::links::DownloadLinks::new(
+                &opt.public_url,
+                opt.download_expiry,
+            ),
             appwrite_endpoint: from_env!("APPWRITE_ENDPOINT"),
             appwrite_project: from_env!("APPWRITE_PROJECT"),
             appwrite_key: from_env!("APPWRITE_API_KEY"),
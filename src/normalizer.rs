@@ -0,0 +1,167 @@
+use std::str::FromStr;
+
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+/// Ligatures which are letters of their own, and are therefore not
+/// expanded by Unicode normalization
+const LIGATURES: [(char, &str); 6] = [
+    ('œ', "oe"),
+    ('Œ', "OE"),
+    ('æ', "ae"),
+    ('Æ', "AE"),
+    ('ß', "ss"),
+    ('ẞ', "SS"),
+];
+
+/// Normalization applied to words before they are indexed or searched
+///
+/// The same normalization must be applied when indexing documents and
+/// when searching them, otherwise queries may not match the indexed
+/// words. Changing it requires indexing documents again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Normalizer {
+    /// Apply the Unicode NFKC normalization, which composes accented
+    /// characters and replaces compatibility characters such as `ﬁ`
+    /// with their canonical equivalent
+    pub nfkc: bool,
+    /// Remove diacritics, so `école` becomes `ecole`
+    pub diacritics: bool,
+    /// Expand ligatures, so `œuvre` becomes `oeuvre`
+    pub ligatures: bool,
+}
+
+impl Default for Normalizer {
+    fn default() -> Self {
+        Self {
+            nfkc: true,
+            diacritics: false,
+            ligatures: false,
+        }
+    }
+}
+
+impl Normalizer {
+    /// Normalize a text before it is split into words
+    ///
+    /// Only the NFKC normalization is applied, so words keep their
+    /// original form.
+    #[must_use]
+    pub fn normalize_text(self, text: &str) -> String {
+        if self.nfkc {
+            text.nfkc().collect()
+        } else {
+            text.to_string()
+        }
+    }
+
+    /// Fold a word for indexing and searching
+    ///
+    /// Diacritics are removed and ligatures are expanded depending on
+    /// the normalizer’s configuration.
+    #[must_use]
+    pub fn fold(self, word: &str) -> String {
+        let mut word = word.to_string();
+        if self.ligatures {
+            word = word
+                .chars()
+                .map(|c| match LIGATURES.iter().find(|(l, _)| *l == c) {
+                    Some((_, expanded)) => (*expanded).to_string(),
+                    None => c.to_string(),
+                })
+                .collect();
+        }
+        if self.diacritics {
+            word = word
+                .nfd()
+                .filter(|c| !is_combining_mark(*c))
+                .nfc()
+                .collect();
+        }
+        word
+    }
+
+    /// Whether the normalizer folds words at all
+    #[must_use]
+    pub fn folds(self) -> bool {
+        self.diacritics || self.ligatures
+    }
+}
+
+/// Parse a normalizer from a list of steps
+///
+/// Steps are separated by commas, such as `nfkc,diacritics`. Valid
+/// steps are `nfkc`, `diacritics`, and `ligatures`. `none` or an
+/// empty list disables all of them.
+impl FromStr for Normalizer {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut normalizer = Self {
+            nfkc: false,
+            diacritics: false,
+            ligatures: false,
+        };
+        for step in s.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            match step {
+                "nfkc" => normalizer.nfkc = true,
+                "diacritics" => normalizer.diacritics = true,
+                "ligatures" => normalizer.ligatures = true,
+                "none" => {}
+                _ => return Err(format!("Unknown normalization step {step}")),
+            }
+        }
+        Ok(normalizer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nfkc_replaces_compatibility_characters() {
+        let normalizer = Normalizer::default();
+        assert_eq!(normalizer.normalize_text("ﬁn e\u{301}té"), "fin été");
+        let none = "none".parse::<Normalizer>().unwrap();
+        assert_eq!(none.normalize_text("ﬁn"), "ﬁn");
+    }
+
+    #[test]
+    fn default_does_not_fold() {
+        let normalizer = Normalizer::default();
+        assert!(!normalizer.folds());
+        assert_eq!(normalizer.fold("Œuvre éditée"), "Œuvre éditée");
+    }
+
+    #[test]
+    fn diacritics_are_removed() {
+        let normalizer = "diacritics".parse::<Normalizer>().unwrap();
+        assert!(normalizer.folds());
+        assert_eq!(normalizer.fold("École Noël ça"), "Ecole Noel ca");
+        assert_eq!(normalizer.fold("œuvre"), "œuvre");
+    }
+
+    #[test]
+    fn ligatures_are_expanded() {
+        let normalizer = "ligatures".parse::<Normalizer>().unwrap();
+        assert_eq!(
+            normalizer.fold("Œuvre cæcum Straße"),
+            "OEuvre caecum Strasse"
+        );
+    }
+
+    #[test]
+    fn steps_are_parsed() {
+        let normalizer = " nfkc , diacritics,ligatures ".parse::<Normalizer>();
+        assert_eq!(
+            normalizer,
+            Ok(Normalizer {
+                nfkc: true,
+                diacritics: true,
+                ligatures: true,
+            })
+        );
+        assert!("nfd".parse::<Normalizer>().is_err());
+    }
+}
@@ -0,0 +1,154 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use hmac::{Hmac, Mac};
+use rand::Rng;
+use rocket::http::RawStr;
+use sha2::Sha256;
+
+/// Signed and expiring links to download documents
+///
+/// Links to the `/docs/<id>/download` endpoint carry their expiry
+/// date along with a HMAC-SHA256 signature of the document and of
+/// this date, so they can be followed by browsers, which cannot send
+/// the `X-User-Auth` header.
+///
+/// The signing key is generated randomly for each process when the
+/// server starts. Links therefore stop working when the server
+/// restarts, and links signed by one instance of the server are
+/// rejected by the others when several of them run side by side.
+pub struct DownloadLinks {
+    public_url: String,
+    /// Lifetime of a link in seconds
+    expiry: u64,
+    /// HMAC-SHA256 keyed with the signing key
+    mac: Hmac<Sha256>,
+}
+
+impl DownloadLinks {
+    /// Sign links under `public_url`, valid for `expiry` seconds
+    #[must_use]
+    pub fn new(public_url: &str, expiry: u64) -> Self {
+        let mut key = [0; 64];
+        rand::thread_rng().fill(&mut key[..]);
+        Self {
+            public_url: public_url.trim_end_matches('/').to_string(),
+            expiry,
+            mac: Hmac::new(&key.into()),
+        }
+    }
+
+    /// Link to download the document `id`
+    #[must_use]
+    pub fn url(&self, id: &str) -> String {
+        let expires = now() + self.expiry;
+        format!(
+            "{}/docs/{}/download?expires={expires}&signature={}",
+            self.public_url,
+            RawStr::new(id).percent_encode(),
+            self.sign(id, expires)
+        )
+    }
+
+    /// Check that `signature` was generated for a link to the
+    /// document `id` expiring at `expires`, and that this link has
+    /// not expired yet
+    ///
+    /// Signatures are compared in constant time.
+    #[must_use]
+    pub fn verify(&self, id: &str, expires: u64, signature: &str) -> bool {
+        let Some(signature) = decode_hex(signature) else {
+            return false;
+        };
+        expires >= now()
+            && self.mac(id, expires).verify_slice(&signature).is_ok()
+    }
+
+    /// HMAC-SHA256 of the document `id` and of the expiry date
+    /// `expires`
+    fn mac(&self, id: &str, expires: u64) -> Hmac<Sha256> {
+        let mut mac = self.mac.clone();
+        mac.update(format!("{id}\n{expires}").as_bytes());
+        mac
+    }
+
+    /// Signature of a link to the document `id` expiring at `expires`,
+    /// in hexadecimal
+    fn sign(&self, id: &str, expires: u64) -> String {
+        format!("{:x}", self.mac(id, expires).finalize().into_bytes())
+    }
+}
+
+/// Decode a hexadecimal string, or return `None` if it is not one
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    hex.as_bytes()
+        .chunks(2)
+        .map(|pair| {
+            if pair.len() != 2 || !pair.iter().all(u8::is_ascii_hexdigit) {
+                return None;
+            }
+            u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok()
+        })
+        .collect()
+}
+
+/// Current time in seconds since the Unix epoch
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signed_links_are_valid() {
+        let links = DownloadLinks::new("http://localhost:8000/", 60);
+        let url = links.url("a b.pdf");
+        assert!(url.starts_with("http://localhost:8000/docs/a%20b.pdf/"));
+        let expires = now() + 60;
+        assert!(links.verify(
+            "a b.pdf",
+            expires,
+            &links.sign("a b.pdf", expires)
+        ));
+    }
+
+    #[test]
+    fn expired_links_are_rejected() {
+        let links = DownloadLinks::new("http://localhost:8000", 60);
+        let expires = now() - 1;
+        assert!(!links.verify(
+            "doc.pdf",
+            expires,
+            &links.sign("doc.pdf", expires)
+        ));
+    }
+
+    #[test]
+    fn tampered_signatures_are_rejected() {
+        let links = DownloadLinks::new("http://localhost:8000", 60);
+        let expires = now() + 60;
+        let signature = links.sign("doc.pdf", expires);
+        let last = if signature.ends_with('0') { "1" } else { "0" };
+        let tampered = format!("{}{last}", &signature[..signature.len() - 1]);
+        assert!(!links.verify("doc.pdf", expires, &tampered));
+        assert!(!links.verify("other.pdf", expires, &signature));
+        assert!(!links.verify("doc.pdf", expires + 1, &signature));
+        assert!(!links.verify("doc.pdf", expires, &signature[1..]));
+        assert!(!links.verify("doc.pdf", expires, "é"));
+    }
+
+    #[test]
+    fn links_of_other_processes_are_rejected() {
+        let links = DownloadLinks::new("http://localhost:8000", 60);
+        let other = DownloadLinks::new("http://localhost:8000", 60);
+        let expires = now() + 60;
+        assert!(!links.verify(
+            "doc.pdf",
+            expires,
+            &other.sign("doc.pdf", expires)
+        ));
+    }
+}
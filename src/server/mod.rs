@@ -1,37 +1,73 @@
 use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
 
 use color_eyre::eyre::Result;
 use diesel::pg::PgConnection;
 use diesel::r2d2::{ConnectionManager, Pool, PooledConnection};
-use rocket::fs::TempFile;
-use rocket::http::Status;
+use memmap2::Mmap;
+use rocket::data::{ByteUnit, Data, Limits};
+use rocket::http::{ContentType, Status};
 use rocket::response::status::Custom;
+use rocket::response::Redirect;
 use rocket::serde::{json::Json, Deserialize, Serialize};
 use rocket::State;
-use tracing::{debug, info};
+use tempfile::NamedTempFile;
+use tracing::{debug, info, warn};
 
-use crate::db::models::DocType;
+use crate::db::models::{
+    DocType, DocumentChanges, SynonymChanges, SynonymGroup, TitleSource,
+};
+use crate::db::weights::FieldWeights;
 use crate::db::{self, models::Document};
-use crate::fileparser::get_content;
-use crate::kwparser;
-use crate::spelling::Dictionary;
+use crate::fileparser::{self, format::Format, get_content, ParsedDocument};
+use crate::language::{self, Languages};
+use crate::spelling::Suggestion;
+use crate::synonyms::{ExpandedQuery, Synonyms};
 
 use self::appwrite::UserSession;
+use self::links::DownloadLinks;
+use self::storage::Storage;
 
-mod appwrite;
-pub mod s3;
+macro_rules! api_error {
+    ($message:expr) => {
+        Custom(Status::InternalServerError, $message)
+    };
+}
 
-extern crate s3 as s3rust;
+/// Acquire `$lock`, turning a poisoned lock into an error naming the
+/// lock `$name`, built by `$error` from its message or an API error
+/// by default
+macro_rules! lock {
+    ($lock:expr, $name:literal) => {
+        lock!($lock, $name, |message| api_error!(message))
+    };
+    ($lock:expr, $name:literal, $error:expr) => {
+        $lock.map_err(|e| $error(format!("{} lock poisoned: {e}", $name)))
+    };
+}
+
+mod appwrite;
+pub mod links;
+pub mod storage;
 
 type DbPool = PooledConnection<ConnectionManager<PgConnection>>;
 
+/// Number of spelling suggestions returned for a word by default
+const SUGGESTIONS: usize = 5;
+
 #[allow(clippy::module_name_repetitions)]
 pub struct ServerState {
-    pub dictionary: Option<Dictionary>,
-    pub glaff: Option<HashMap<String, String>>,
+    pub languages: Arc<Languages>,
     pub pool: Pool<ConnectionManager<PgConnection>>,
-    pub stopwords: Vec<String>,
-    pub s3_bucket: s3rust::Bucket,
+    pub description_length: usize,
+    pub weights: FieldWeights,
+    pub synonyms: RwLock<Synonyms>,
+    /// Probability above which a spelling suggestion is offered for a
+    /// query
+    pub spelling_confidence: f64,
+    pub storage: Arc<dyn Storage>,
+    /// Signed links to download documents without a public URL
+    pub links: DownloadLinks,
     pub appwrite_endpoint: String,
     pub appwrite_project: String,
     pub appwrite_key: String,
@@ -46,32 +82,65 @@ pub struct RankedDoc {
     pub description: String,
     pub hits: Option<i32>,
     pub online: bool,
+    pub language: Option<String>,
+    pub title_source: TitleSource,
 }
 
-impl From<Document> for RankedDoc {
-    fn from(doc: Document) -> Self {
+impl RankedDoc {
+    /// Create a `RankedDoc` from a document
+    ///
+    /// Online documents are available at their own URL while offline
+    /// documents are downloaded from the public URL of the storage
+    /// backend if it has one, or through a signed link to
+    /// [`download_document`] otherwise.
+    ///
+    /// [`download_document`]: ./fn.download_document.html
+    #[must_use]
+    pub fn new(doc: Document, state: &ServerState) -> Self {
         Self {
             doc: doc.name.clone(),
             url: if doc.doctype == DocType::Online {
                 doc.name
             } else {
-                format!(
-                    "https://{}.{}/{}",
-                    std::env::var("S3_BUCKET_ID")
-                        .expect("S3_BUCKET_ID must be set!"),
-                    std::env::var("S3_ENDPOINT")
-                        .expect("S3_ENDPOINT must be set!"),
-                    doc.name
-                )
+                state
+                    .storage
+                    .url(&doc.name)
+                    .unwrap_or_else(|| state.links.url(&doc.name))
             },
             title: doc.title,
             description: doc.description,
             hits: None,
             online: doc.doctype == DocType::Online,
+            language: doc.language,
+            title_source: doc.title_source,
         }
     }
 }
 
+/// Keyword manually associated with a document
+///
+/// If its `weight` is not set, [`db::DEFAULT_MANUAL_WEIGHT`] is used.
+///
+/// [`db::DEFAULT_MANUAL_WEIGHT`]: ../db/constant.DEFAULT_MANUAL_WEIGHT.html
+#[derive(Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct ManualKeyword {
+    pub word: String,
+    pub weight: Option<i32>,
+}
+
+/// Changes to apply to a document
+///
+/// Fields which are not set are left untouched. If `keywords` is set,
+/// it replaces all keywords previously set manually on the document.
+#[derive(Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct DocumentUpdate {
+    #[serde(flatten)]
+    pub changes: DocumentChanges,
+    pub keywords: Option<Vec<ManualKeyword>>,
+}
+
 #[derive(Serialize, Deserialize)]
 #[serde(crate = "rocket::serde")]
 pub struct RankedKeyword {
@@ -79,10 +148,24 @@ pub struct RankedKeyword {
     pub rank: i32,
 }
 
+/// Candidate corrections of a word of a query
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct SpellingCandidates {
+    pub word: String,
+    pub suggestions: Vec<Suggestion>,
+}
+
 #[derive(Serialize, Default)]
 #[serde(crate = "rocket::serde")]
 pub struct QueryResult {
     spelling_suggestion: Option<String>,
+    /// Probability that the query was meant to be the spelling
+    /// suggestion
+    spelling_confidence: Option<f64>,
+    /// Candidate corrections of the words of the query missing from
+    /// the dictionary
+    spelling_candidates: Vec<SpellingCandidates>,
     results: Vec<RankedDoc>,
     using_suggestion: bool,
 }
@@ -101,6 +184,7 @@ impl QueryResult {
                 UseSpellingSuggestion::Yes => true,
                 UseSpellingSuggestion::No => false,
             },
+            ..Default::default()
         }
     }
 }
@@ -110,12 +194,6 @@ pub enum UseSpellingSuggestion {
     No,
 }
 
-macro_rules! api_error {
-    ($message:expr) => {
-        Custom(Status::InternalServerError, $message)
-    };
-}
-
 macro_rules! get_connector {
     ($db:expr) => {
         match $db.pool.get() {
@@ -139,33 +217,85 @@ macro_rules! json_val_or_error {
 
 pub type ApiResponse<T> = std::result::Result<T, Custom<String>>;
 
-async fn fetch_content(url: &String) -> ApiResponse<Vec<u8>> {
+/// Download the document at `url`
+///
+/// Return its content along with the value of the `Content-Type`
+/// header it was served with, if any.
+async fn fetch_content(url: &String) -> ApiResponse<(Vec<u8>, Option<String>)> {
     match reqwest::get(url).await {
-        Ok(val) => match val.bytes().await {
-            Ok(val) => Ok(val.into()),
-            Err(e) => Err(Custom(
-                Status::NotAcceptable,
-                format!("Cannot retrieve bytes from requested document; {e}"),
-            )),
-        },
+        Ok(val) => {
+            let content_type = val
+                .headers()
+                .get(reqwest::header::CONTENT_TYPE)
+                .and_then(|header| header.to_str().ok())
+                .map(ToString::to_string);
+            match val.bytes().await {
+                Ok(val) => Ok((val.into(), content_type)),
+                Err(e) => Err(Custom(
+                    Status::NotAcceptable,
+                    format!(
+                        "Cannot retrieve bytes from requested document; {e}"
+                    ),
+                )),
+            }
+        }
         Err(e) => Err(Custom(Status::InternalServerError, e.to_string())),
     }
 }
 
+/// Parse the format of a document requested by the user
+///
+/// # Errors
+///
+/// If the format is not supported, return a 400 error.
+fn parse_format(format: Option<&str>) -> ApiResponse<Option<Format>> {
+    format
+        .map(str::parse)
+        .transpose()
+        .map_err(|e| Custom(Status::BadRequest, e))
+}
+
 // Inserting into the database ////////////////////////////////////////////////
 
+/// Index a document and add it to the database
+///
+/// The document is stored in the database as `identifier`. If no
+/// title can be found in the document, `fallback_title` is used
+/// instead. `content_type` is the MIME type the document was served
+/// or uploaded with, if any, and `format` the format requested by the
+/// user, if any.
 fn index_file(
     state: &State<ServerState>,
     file: &[u8],
+    content_type: Option<&str>,
+    format: Option<Format>,
+    identifier: &str,
+    fallback_title: &str,
+    file_type: DocType,
+) -> ApiResponse<()> {
+    let content = get_content(
+        file,
+        content_type,
+        format,
+        fallback_title,
+        state.description_length,
+        &state.languages,
+    )
+    .map_err(|e| Custom(Status::NotAcceptable, format!("{e:?}")))?;
+    insert_document(&state.pool, &content, identifier, file_type)
+}
+
+/// Add a parsed document and its keywords to the database
+///
+/// The document is stored in the database as `identifier`.
+fn insert_document(
+    pool: &Pool<ConnectionManager<PgConnection>>,
+    content: &ParsedDocument,
     identifier: &str,
     file_type: DocType,
 ) -> ApiResponse<()> {
-    let stop_words = &state.stopwords;
-    let glaff = &state.glaff;
-    let content = get_content(file, stop_words, glaff)
-        .map_err(|e| Custom(Status::NotAcceptable, format!("{e:?}")))?;
     debug!("{:?}", content);
-    let conn = &mut state.pool.get().map_err(|e| {
+    let conn = &mut pool.get().map_err(|e| {
         api_error!(format!("Failed to connect to the database: {e}"))
     })?;
     info!("== Inserting {} in database", &identifier);
@@ -175,8 +305,10 @@ fn index_file(
         name: identifier.to_string(),
         doctype: file_type,
         description: content.description.clone(),
+        language: content.language.clone(),
+        title_source: content.title_source,
     };
-    db::add_document(conn, &doc, &content).map_err(|e| {
+    db::add_document(conn, &doc, content).map_err(|e| {
         Custom(
             Status::InternalServerError,
             format!("Failed to insert URL {identifier} as a document: {e}"),
@@ -202,59 +334,145 @@ where
     Custom(Status::InternalServerError, e.to_string())
 }
 
-async fn file_to_vec(mut file: TempFile<'_>) -> ApiResponse<Vec<u8>> {
-    file.move_copy_to("tmp/file")
-        .await
-        .map_err(simple_internal_error)?;
-    let file = std::fs::read("tmp/file").map_err(simple_internal_error)?;
-    std::fs::remove_file("tmp/file").map_err(simple_internal_error)?;
-    debug!("Deleted temporary file");
-    Ok(file)
+/// Stream uploaded data to a unique temporary file
+///
+/// The data is hashed while it is written to the temporary file,
+/// which is deleted once dropped. Return the temporary file along
+/// with the sha256 sum of its content.
+///
+/// # Errors
+///
+/// If the data is larger than `limit`, a 413 error is returned. Any
+/// error reading the data or writing the temporary file is wrapped in
+/// a 500 error.
+async fn stream_to_temp_file(
+    data: Data<'_>,
+    limit: ByteUnit,
+) -> ApiResponse<(NamedTempFile, String)> {
+    use rocket::tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use sha2::{Digest, Sha256};
+
+    let temp = NamedTempFile::new().map_err(simple_internal_error)?;
+    let mut file = rocket::tokio::fs::File::from_std(
+        temp.reopen().map_err(simple_internal_error)?,
+    );
+    // Read one byte past the limit to detect oversized uploads
+    let mut stream = data.open(limit + 1);
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; 64 * 1024];
+    let mut size: u64 = 0;
+    loop {
+        let read = stream
+            .read(&mut buffer)
+            .await
+            .map_err(simple_internal_error)?;
+        if read == 0 {
+            break;
+        }
+        size += read as u64;
+        if size > limit.as_u64() {
+            return Err(Custom(
+                Status::PayloadTooLarge,
+                format!("File is larger than the limit of {limit}"),
+            ));
+        }
+        hasher.update(&buffer[..read]);
+        file.write_all(&buffer[..read])
+            .await
+            .map_err(simple_internal_error)?;
+    }
+    file.flush().await.map_err(simple_internal_error)?;
+    debug!("Streamed {size} bytes to {}", temp.path().display());
+    Ok((temp, format!("{:x}", hasher.finalize())))
 }
 
 /// Upload and index a document
 ///
-/// The `file` transmitted as pure data is uploaded to a S3 bucket and
-/// then parsed. If any error arise when indexing the document, the
-/// object on the S3 bucket is then deleted. Otherwise, its name on
-/// the bucket, its sha256 sum concatenated with its filename, is
-/// stored as the document’s name.
+/// The `file` transmitted as pure data is streamed to a temporary
+/// file, uploaded to the server’s storage backend and then parsed
+/// from a memory map of the temporary file, so that it is never read
+/// into memory as a whole. It is parsed and its keywords are inserted
+/// on a blocking thread, so the async runtime is not held up.
+/// Its size may not exceed the `file` limit set in `Rocket.toml`. If
+/// any error arise when indexing the document, the stored file is
+/// then deleted. Otherwise, its name in the storage, its sha256 sum
+/// concatenated with its filename, is stored as the document’s name.
+///
+/// The format of the document is detected from its content, its
+/// `Content-Type`, and the extension of `filename`, unless it is
+/// given by the `format` query parameter.
 ///
 /// # Errors
 ///
-/// If any error arise from the indexation of the file, if the file
-/// fails to upload to the S3 bucket or fails to be deleted from it,
-/// the error is wrapped in a 500 Rocket error and returned to the
-/// user. For more information, see `s3::upload_fle`,
-/// `s3::delete_file`, and `index_file`.
-#[post("/docs/file/<filename>", data = "<file>")]
+/// If `format` is not supported, a 400 error is returned. If the file
+/// is too large, a 413 error is returned. If any error
+/// arise from the indexation of the file, if the file fails to upload
+/// to the storage backend or fails to be deleted from it, the error
+/// is wrapped in a 500 Rocket error and returned to the user. For
+/// more information, see [`Storage`] and `index_file`.
+///
+/// [`Storage`]: ./storage/trait.Storage.html
+#[post("/docs/file/<filename>?<format>", data = "<file>")]
 pub async fn index_upload(
     state: &State<ServerState>,
-    file: TempFile<'_>,
+    limits: &Limits,
+    content_type: Option<&ContentType>,
+    file: Data<'_>,
     filename: String,
+    format: Option<&str>,
     _auth: UserSession<'_>,
 ) -> ApiResponse<()> {
-    use sha256::digest;
-    let file = file_to_vec(file).await?;
-    let id = digest(&file as &[u8]);
+    let format = parse_format(format)?;
+    let limit = limits.get("file").unwrap_or(Limits::FILE);
+    let (temp, id) = stream_to_temp_file(file, limit).await?;
+    let fallback_title = filename.clone();
     let filename = format!("{id}-{filename}");
 
     info!("Uploading file {filename}");
-    s3::upload_file(state, filename.clone(), file.as_slice()).await?;
+    state
+        .storage
+        .upload(&filename, temp.path())
+        .await
+        .map_err(simple_internal_error)?;
 
     info!("Indexing {filename}");
-    match index_file(state, &file, &filename, DocType::Offline) {
-        Ok(_) => Ok(()),
+    let content_type = content_type.map(ToString::to_string);
+    let pool = state.pool.clone();
+    let languages = Arc::clone(&state.languages);
+    let description_length = state.description_length;
+    let identifier = filename.clone();
+    let indexed = rocket::tokio::task::spawn_blocking(move || {
+        // SAFETY: the temporary file is only read, and nothing else
+        // writes to it until it is dropped.
+        let file = unsafe { Mmap::map(temp.as_file()) }
+            .map_err(simple_internal_error)?;
+        let content = get_content(
+            &file,
+            content_type.as_deref(),
+            format,
+            &fallback_title,
+            description_length,
+            &languages,
+        )
+        .map_err(|e| Custom(Status::NotAcceptable, format!("{e:?}")))?;
+        insert_document(&pool, &content, &identifier, DocType::Offline)
+    })
+    .await
+    .map_err(simple_internal_error)?;
+    match indexed {
+        Ok(()) => Ok(()),
         Err(error_index) => {
             info!(
-                "Could not index file: {error_index:?}. Deleting {filename} from s3 storage",
+                "Could not index file: {error_index:?}. Deleting {filename} from storage",
             );
-            s3::delete_file(state, filename)
+            state
+                .storage
+                .delete(&filename)
                 .await
                 .map_err(|error_delete| {
                     Custom(
                         Status::InternalServerError,
-                        format!("{error_index:?}\tAND\t{}", error_delete.1),
+                        format!("{error_index:?}\tAND\t{error_delete}"),
                     )
                 })
         }
@@ -267,20 +485,35 @@ pub async fn index_upload(
 /// The URL **must** be an encoded url such what `encodeURIComponent`
 /// in Javascript results to.
 ///
+/// The format of the document is detected from its content, the
+/// `Content-Type` it is served with, and the extension of the URL,
+/// unless it is given by the `format` query parameter.
+///
 /// # Errors
 ///
-/// Errors might originate from the database, Diesel, or Rocket
-#[post("/docs/url/<url>")]
+/// If `format` is not supported, a 400 error is returned. Other
+/// errors might originate from the database, Diesel, or Rocket
+#[post("/docs/url/<url>?<format>")]
 pub async fn index_url(
     url: String,
+    format: Option<&str>,
     state: &State<ServerState>,
     _auth: UserSession<'_>,
 ) -> ApiResponse<()> {
+    let format = parse_format(format)?;
     info!("Indexing URL {}", &url);
     info!("== Downloading {}", &url);
-    let document = fetch_content(&url).await?;
+    let (document, content_type) = fetch_content(&url).await?;
     info!("== Downloaded {}", &url);
-    index_file(state, &document, url.as_str(), DocType::Online)?;
+    index_file(
+        state,
+        &document,
+        content_type.as_deref(),
+        format,
+        &url,
+        &url,
+        DocType::Online,
+    )?;
     Ok(())
 }
 
@@ -299,11 +532,11 @@ pub async fn delete_document(
 ) -> ApiResponse<()> {
     info!("Deleting document \"{id}\"");
     let conn = &mut get_connector!(state);
-    if let Some(filename) = db::get_s3_filename(conn, id) {
-        s3::delete_file(state, filename).await.map_err(|e| {
+    if let Some(filename) = db::get_stored_filename(conn, id) {
+        state.storage.delete(&filename).await.map_err(|e| {
             Custom(
                 Status::InternalServerError,
-                format!("Failed to delete remote file in S3 bucket: {e:?}"),
+                format!("Failed to delete stored file: {e}"),
             )
         })?;
     }
@@ -314,22 +547,140 @@ pub async fn delete_document(
         .map_err(|e| api_error!(e.to_string()))
 }
 
+// Updating the database //////////////////////////////////////////////////////
+
+/// Check and normalize changes to a document sent by the user
+///
+/// The whitespace of the title is collapsed, and the language is
+/// normalized like the ones of parsed documents.
+///
+/// # Errors
+///
+/// If the title is empty, or if the language is not a valid language
+/// tag, return a 400 error.
+fn normalize_document_changes(
+    changes: DocumentChanges,
+) -> ApiResponse<DocumentChanges> {
+    let title = changes
+        .title
+        .map(|title| {
+            fileparser::clean_title(&title).ok_or_else(|| {
+                Custom(
+                    Status::BadRequest,
+                    "A document title cannot be empty".to_string(),
+                )
+            })
+        })
+        .transpose()?;
+    let language = changes
+        .language
+        .map(|tag| {
+            language::normalize_tag(&tag).ok_or_else(|| {
+                Custom(Status::BadRequest, format!("Invalid language {tag}"))
+            })
+        })
+        .transpose()?;
+    Ok(DocumentChanges {
+        title,
+        language,
+        ..changes
+    })
+}
+
+/// Update the metadata of the document `id`
+///
+/// The title, description, and language of the document can be
+/// changed, as well as its manual keywords. Manual keywords are
+/// normalized the same way keywords extracted from documents are, in
+/// the language of the document, but they are stored separately with
+/// their own weight. Return the updated document.
+///
+/// # Errors
+///
+/// If the title is empty or the language is invalid, a 400 error is
+/// returned, see [`normalize_document_changes`]. If the document does
+/// not exist, a 404 error is returned. Other errors might originate
+/// from the database, Diesel, or Rocket
+///
+/// [`normalize_document_changes`]: ./fn.normalize_document_changes.html
+#[patch("/docs/<id>", data = "<update>")]
+pub fn update_document(
+    id: &str,
+    update: Json<DocumentUpdate>,
+    state: &State<ServerState>,
+    _auth: UserSession<'_>,
+) -> ApiResponse<Json<RankedDoc>> {
+    info!("Updating document \"{id}\"");
+    let conn = &mut get_connector!(state);
+    let update = update.into_inner();
+    let changes = normalize_document_changes(update.changes)?;
+    let language = changes.language.clone().or_else(|| {
+        db::get_document(conn, id).and_then(|document| document.language)
+    });
+    let pipeline = state.languages.pipeline(language.as_deref());
+    let keywords = update.keywords.map(|keywords| {
+        keywords
+            .into_iter()
+            .map(|keyword| {
+                let form = keyword.word.trim().to_lowercase();
+                (
+                    pipeline.analyzer.term(&form),
+                    form,
+                    keyword.weight.unwrap_or(db::DEFAULT_MANUAL_WEIGHT),
+                )
+            })
+            .filter(|(word, _, _)| !word.is_empty())
+            .collect::<Vec<(String, String, i32)>>()
+    });
+    match db::update_document(conn, id, &changes, keywords.as_deref()) {
+        Ok(document) => {
+            info!("Updated document \"{id}\"");
+            Ok(Json(RankedDoc::new(document, state)))
+        }
+        Err(diesel::NotFound) => {
+            Err(Custom(Status::NotFound, format!("Document {id} not found")))
+        }
+        Err(e) => Err(api_error!(e.to_string())),
+    }
+}
+
 // Reading the database ///////////////////////////////////////////////////////
 
+/// Turn documents and their hits into a list of [`RankedDoc`]
+///
+/// [`RankedDoc`]: ./struct.RankedDoc.html
+fn rank_documents(
+    docs: Vec<(Document, i32)>,
+    state: &ServerState,
+) -> Vec<RankedDoc> {
+    docs.into_iter()
+        .map(|(doc, hits)| RankedDoc {
+            hits: Some(hits),
+            ..RankedDoc::new(doc, state)
+        })
+        .collect()
+}
+
 fn search_document_by_keyword(
     conn: &mut DbPool,
-    query: &[String],
-    spelling_suggestion: &[String],
+    state: &ServerState,
+    query: &ExpandedQuery,
+    spelling_suggestion: &ExpandedQuery,
+    suggestion: &str,
     using_suggestion: &UseSpellingSuggestion,
 ) -> Result<Json<QueryResult>> {
     match using_suggestion {
         // If we are already using the spelling suggestion, return
         // what we have
         UseSpellingSuggestion::Yes => {
-            let results = db::keywords_search(conn, spelling_suggestion)?;
+            let results = db::keywords_search(
+                conn,
+                &spelling_suggestion.words,
+                &state.weights,
+            )?;
             Ok(Json(QueryResult::new(
-                results,
-                Some(spelling_suggestion.join(" ")),
+                rank_documents(results, state),
+                Some(suggestion.to_string()),
                 using_suggestion,
             )))
         }
@@ -337,18 +688,26 @@ fn search_document_by_keyword(
         UseSpellingSuggestion::No => {
             // If the results are not empty, or if the spelling
             // suggestion bears no difference with the initial query,
-            // return what we have
-            let results = db::keywords_search(conn, query)?;
-            if !results.is_empty() || query == spelling_suggestion {
-                Ok(Json(QueryResult::new(results, None, using_suggestion)))
+            // return what we have, along with the suggestion if any
+            let results =
+                db::keywords_search(conn, &query.words, &state.weights)?;
+            if !results.is_empty() || query.terms == spelling_suggestion.terms {
+                Ok(Json(QueryResult::new(
+                    rank_documents(results, state),
+                    (query.terms != spelling_suggestion.terms)
+                        .then(|| suggestion.to_string()),
+                    using_suggestion,
+                )))
             } else {
                 // Otherwise, if the results were empty and the
                 // initial query is different from the spelling
                 // suggestion, try to search the database using it
                 search_document_by_keyword(
                     conn,
+                    state,
                     query,
                     spelling_suggestion,
+                    suggestion,
                     &UseSpellingSuggestion::Yes,
                 )
             }
@@ -358,23 +717,35 @@ fn search_document_by_keyword(
 
 /// Search documents matching the keywords in `query`
 ///
-/// This function also executes a spell check on the query. If the
-/// function detects no results are found from the initial query, it
-/// will try to find other results using the spell checked version of
-/// the query. Whether the spell checked version of the query has been
-/// used or not is specified in the [`QueryResult`] type returned.
+/// This function also executes a spell check on the query. Its words
+/// are corrected in context, see [`Dictionary::correct_phrase`].
+/// Only above the configured confidence is the spell checked version
+/// suggested. If the function detects no results are
+/// found from the initial query, it will then try to find other
+/// results using the spell checked version of the query. Whether the
+/// spell checked version of the query has been used or not is
+/// specified in the [`QueryResult`] type returned, along with the
+/// candidate corrections of each word.
+///
+/// The query is analysed in the language `lang` if given, otherwise
+/// in the language detected from the query, see
+/// [`Languages::query_language`]. Its words are then expanded with
+/// their synonyms in this language, which weigh less than the words of
+/// the query.
 ///
 /// # Errors
 ///
 /// Errors might originate from the database, Diesel, or Rocket
 ///
 /// [`QueryResult`]: ./struct.QueryResult.html
-#[get("/search/<query>")]
+/// [`Dictionary::correct_phrase`]: ../spelling/struct.Dictionary.html#method.correct_phrase
+/// [`Languages::query_language`]: ../language/struct.Languages.html#method.query_language
+#[get("/search/<query>?<lang>")]
 pub fn search_query(
     query: &str,
+    lang: Option<&str>,
     state: &State<ServerState>,
 ) -> ApiResponse<Json<QueryResult>> {
-    use crate::spelling::correct;
     // Filter out empty queries
     info!("Query \"{query}\"");
     if query.is_empty() {
@@ -384,33 +755,76 @@ pub fn search_query(
     let conn = &mut get_connector!(state);
 
     // Normalize query
-    let glaff = &state.glaff;
-    let query_vec = query
-        .split_whitespace()
-        .map(|s| kwparser::get_lemma_from_glaff(s.to_lowercase(), glaff))
+    let requested = state.languages.query_language(lang, query);
+    let language = state.languages.resolve(Some(&requested));
+    debug!("Language of the query: {requested}, analysed as {language}");
+    let pipeline = state.languages.pipeline(Some(language));
+    let analyzer = &pipeline.analyzer;
+    let terms = analyzer.keywords(query);
+    let query_vec = terms
+        .iter()
+        .map(|term| term.word.clone())
         .collect::<Vec<String>>();
 
-    // Spellcheck query
+    // Spellcheck query before normalizing it, as stems are not words.
+    // Words are corrected in context, stop words included, and may be
+    // split or merged. Numbers and URLs are left as is. The suggestion
+    // is made of the corrected words, its terms are searched.
     debug!("Normalized query_vec: {query_vec:?}");
-    let spelling_suggestion = query_vec
-        .iter()
-        .map(|s| {
-            kwparser::get_lemma_from_glaff(
-                correct(s.to_string(), &state.dictionary),
-                glaff,
-            )
-        })
-        .collect::<Vec<String>>();
+    let mut confidence = 1.0;
+    let mut candidates = Vec::new();
+    let mut spelling_suggestion = query_vec.clone();
+    let mut suggestion = String::new();
+    if let Some(dictionary) = pipeline.dictionary() {
+        let forms = analyzer.forms(query).collect::<Vec<String>>();
+        let forms = forms.iter().map(String::as_str).collect::<Vec<_>>();
+        let correction = dictionary.correct_phrase(&forms);
+        confidence = correction.confidence;
+        debug!("Spelling confidence: {confidence}");
+        if confidence >= state.spelling_confidence {
+            suggestion = correction.words.join(" ");
+            spelling_suggestion = analyzer
+                .keywords(&suggestion)
+                .into_iter()
+                .map(|term| term.word)
+                .collect();
+        }
+        candidates = terms
+            .iter()
+            .filter(|term| {
+                term.form.chars().all(char::is_alphabetic)
+                    && !dictionary.words.contains_key(&term.form)
+            })
+            .map(|term| SpellingCandidates {
+                word: term.form.clone(),
+                suggestions: dictionary.suggestions(&term.form, SUGGESTIONS),
+            })
+            .collect();
+    }
 
-    // Execute the query
+    // Expand the query with synonyms
     debug!("Suggested query: {spelling_suggestion:?}");
-    search_document_by_keyword(
+    let synonyms = lock!(state.synonyms.read(), "Synonyms")?;
+    let query_vec = synonyms.expand(language, query_vec);
+    let spelling_suggestion = synonyms.expand(language, spelling_suggestion);
+    drop(synonyms);
+    debug!("Expanded query: {:?}", query_vec.words);
+
+    // Execute the query
+    let mut result = search_document_by_keyword(
         conn,
+        state,
         &query_vec,
         &spelling_suggestion,
+        &suggestion,
         &UseSpellingSuggestion::No,
     )
-    .map_err(|e| Custom(Status::InternalServerError, e.to_string()))
+    .map_err(|e| Custom(Status::InternalServerError, e.to_string()))?;
+    if result.spelling_suggestion.is_some() {
+        result.spelling_confidence = Some(confidence);
+    }
+    result.spelling_candidates = candidates;
+    Ok(result)
 }
 
 /// List indexed documents
@@ -426,8 +840,8 @@ pub fn list_docs(
     info!("Listing documents");
     let conn = &mut get_connector!(state);
     json_val_or_error!(db::list_documents(conn).map(|docs| docs
-        .iter()
-        .map(|doc| doc.clone().into())
+        .into_iter()
+        .map(|doc| RankedDoc::new(doc, state))
         .collect::<Vec<RankedDoc>>()))
 }
 
@@ -443,12 +857,563 @@ pub fn document_list_keywords(
 ) -> ApiResponse<Json<Vec<RankedKeyword>>> {
     info!("Getting document \"{doc}\"");
     let conn = &mut get_connector!(state);
-    json_val_or_error!(db::doc_list_keywords(conn, doc))
+    json_val_or_error!(db::doc_list_keywords(conn, doc, &state.weights))
+}
+
+/// Download of a document
+#[derive(Responder)]
+pub enum Download {
+    /// Redirection to the URL at which the document is available
+    Redirect(Redirect),
+    /// Content of a document served by the indexer itself
+    File((ContentType, Vec<u8>)),
+}
+
+/// Download the document `id`
+///
+/// The link must have been signed by the indexer and not have expired
+/// yet, see [`DownloadLinks`]. Such links are returned along with
+/// documents, and can be followed without the `X-User-Auth` header.
+///
+/// Redirect the user to the URL at which the document can be
+/// downloaded. For offline documents, this URL is generated by the
+/// storage backend and may expire, which allows the backend to keep
+/// its files private. Offline documents stored without any URL, such
+/// as those stored locally or in memory, are served directly.
+///
+/// # Errors
+///
+/// If the link is not signed or has expired, a 403 error is returned.
+/// If the document does not exist, a 404 error is returned. Errors
+/// might also originate from the database or the storage backend.
+///
+/// [`DownloadLinks`]: ./links/struct.DownloadLinks.html
+#[get("/docs/<id>/download?<expires>&<signature>")]
+pub async fn download_document(
+    id: &str,
+    expires: Option<u64>,
+    signature: Option<&str>,
+    state: &State<ServerState>,
+) -> ApiResponse<Download> {
+    info!("Downloading document \"{id}\"");
+    match (expires, signature) {
+        (Some(expires), Some(signature))
+            if state.links.verify(id, expires, signature) => {}
+        _ => {
+            return Err(Custom(
+                Status::Forbidden,
+                "Invalid or expired download link".to_string(),
+            ))
+        }
+    }
+    let document = {
+        let conn = &mut get_connector!(state);
+        db::get_document(conn, id).ok_or_else(|| {
+            Custom(Status::NotFound, format!("Document {id} not found"))
+        })?
+    };
+    if document.doctype == DocType::Online {
+        return Ok(Download::Redirect(Redirect::temporary(document.name)));
+    }
+    if let Some(url) = state
+        .storage
+        .download_url(&document.name)
+        .map_err(simple_internal_error)?
+    {
+        return Ok(Download::Redirect(Redirect::temporary(url)));
+    }
+    let content_type = std::path::Path::new(&document.name)
+        .extension()
+        .and_then(|ext| ContentType::from_extension(&ext.to_string_lossy()))
+        .unwrap_or(ContentType::Binary);
+    state
+        .storage
+        .get(&document.name)
+        .await
+        .map(|file| Download::File((content_type, file)))
+        .map_err(|e| Custom(Status::NotFound, e.to_string()))
+}
+
+// Stop words /////////////////////////////////////////////////////////////////
+
+/// Result of a change to the stop words of a language
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct StopwordsUpdate {
+    /// Words which were actually added or removed
+    pub changed: Vec<String>,
+    /// Amount of documents being indexed again in the background
+    pub reindexing: usize,
+}
+
+/// Find the language whose stop words are managed
+///
+/// # Errors
+///
+/// If `lang` is not a valid language tag or if the language has no
+/// pipeline, return a 404 error.
+fn stopwords_language(state: &ServerState, lang: &str) -> ApiResponse<String> {
+    language::normalize_tag(lang)
+        .filter(|language| state.languages.has_pipeline(language))
+        .ok_or_else(|| {
+            Custom(Status::NotFound, format!("Language {lang} not found"))
+        })
+}
+
+/// Trim and lowercase words sent by the user, leaving out empty and
+/// duplicate ones
+fn normalize_words(words: &[String]) -> Vec<String> {
+    let mut words = words
+        .iter()
+        .map(|word| word.trim().to_lowercase())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<String>>();
+    words.sort();
+    words.dedup();
+    words
+}
+
+/// Reload the stop words of all languages from the database
+fn load_stopwords(conn: &mut DbPool, state: &ServerState) -> ApiResponse<()> {
+    let stopwords =
+        db::list_stopwords(conn).map_err(|e| api_error!(e.to_string()))?;
+    state.languages.set_stopwords(&stopwords);
+    Ok(())
+}
+
+/// Index `document` again, replacing its keywords
+///
+/// Its content is downloaded again from its URL if it is online, or
+/// read from the storage backend otherwise. It is then parsed and its
+/// keywords are replaced on a blocking thread, so the async runtime
+/// is not held up. Its metadata and manual keywords are left
+/// untouched.
+async fn reindex_document(
+    pool: &Pool<ConnectionManager<PgConnection>>,
+    storage: &dyn Storage,
+    languages: &Arc<Languages>,
+    description_length: usize,
+    document: &Document,
+) -> ApiResponse<()> {
+    let (file, content_type) = match document.doctype {
+        DocType::Online => fetch_content(&document.name).await?,
+        DocType::Offline => (
+            storage
+                .get(&document.name)
+                .await
+                .map_err(simple_internal_error)?,
+            None,
+        ),
+    };
+    let pool = pool.clone();
+    let languages = Arc::clone(languages);
+    let name = document.name.clone();
+    rocket::tokio::task::spawn_blocking(move || {
+        let content = get_content(
+            &file,
+            content_type.as_deref(),
+            None,
+            &name,
+            description_length,
+            &languages,
+        )
+        .map_err(|e| Custom(Status::NotAcceptable, format!("{e:?}")))?;
+        let conn = &mut pool.get().map_err(simple_internal_error)?;
+        db::replace_keywords(conn, &name, &content)
+            .map_err(simple_internal_error)
+    })
+    .await
+    .map_err(simple_internal_error)?
+}
+
+/// Index `documents` again in the background
+///
+/// Documents failing to be indexed again keep their keywords. Return
+/// the amount of documents to index again.
+fn reindex_in_background(
+    state: &ServerState,
+    documents: Vec<Document>,
+) -> usize {
+    let count = documents.len();
+    if count == 0 {
+        return count;
+    }
+    info!("Indexing {count} documents again in the background");
+    let pool = state.pool.clone();
+    let storage = Arc::clone(&state.storage);
+    let languages = Arc::clone(&state.languages);
+    let description_length = state.description_length;
+    rocket::tokio::spawn(async move {
+        for document in documents {
+            match reindex_document(
+                &pool,
+                storage.as_ref(),
+                &languages,
+                description_length,
+                &document,
+            )
+            .await
+            {
+                Ok(()) => info!("Indexed {} again", document.name),
+                Err(e) => {
+                    warn!("Failed to index {} again: {e:?}", document.name);
+                }
+            }
+        }
+        info!("Indexed {count} documents again");
+    });
+    count
+}
+
+/// List the stop words of the language `lang`
+///
+/// # Errors
+///
+/// If the language has no pipeline, a 404 error is returned. Other
+/// errors might originate from the database, Diesel, or Rocket
+#[get("/stopwords/<lang>")]
+pub fn list_stopwords(
+    lang: &str,
+    state: &State<ServerState>,
+    _auth: UserSession<'_>,
+) -> ApiResponse<Json<Vec<String>>> {
+    info!("Listing stop words of {lang}");
+    let language = stopwords_language(state, lang)?;
+    let conn = &mut get_connector!(state);
+    json_val_or_error!(db::list_stopwords(conn).map(|stopwords| stopwords
+        .into_iter()
+        .filter(|(l, _)| *l == language)
+        .map(|(_, word)| word)
+        .collect::<Vec<String>>()))
+}
+
+/// Add stop words to the language `lang`
+///
+/// The stop words are applied right away to documents and queries
+/// analysed in this language. If `reindex` is true, documents holding
+/// any of the new stop words are indexed again in the background.
+///
+/// # Errors
+///
+/// If the language has no pipeline, a 404 error is returned. Other
+/// errors might originate from the database, Diesel, or Rocket
+#[post("/stopwords/<lang>?<reindex>", data = "<words>")]
+pub fn add_stopwords(
+    lang: &str,
+    reindex: Option<bool>,
+    words: Json<Vec<String>>,
+    state: &State<ServerState>,
+    _auth: UserSession<'_>,
+) -> ApiResponse<Json<StopwordsUpdate>> {
+    info!("Adding stop words to {lang}");
+    let language = stopwords_language(state, lang)?;
+    let words = normalize_words(&words.into_inner());
+    let conn = &mut get_connector!(state);
+    let changed = db::update_stopwords(conn, &language, &words, false)
+        .map_err(|e| api_error!(e.to_string()))?;
+    load_stopwords(conn, state)?;
+    let documents = if reindex.unwrap_or(false) && !changed.is_empty() {
+        // Stop words are matched before and after lemmatization
+        let pipeline = state.languages.pipeline(Some(&language));
+        let terms = changed
+            .iter()
+            .flat_map(|word| {
+                [
+                    pipeline.analyzer.term(word),
+                    pipeline.analyzer.normalizer.fold(word),
+                ]
+            })
+            .collect::<Vec<String>>();
+        db::documents_with_keywords(conn, &terms)
+            .map_err(|e| api_error!(e.to_string()))?
+    } else {
+        Vec::new()
+    };
+    Ok(Json(StopwordsUpdate {
+        changed,
+        reindexing: reindex_in_background(state, documents),
+    }))
+}
+
+/// Remove stop words from the language `lang`
+///
+/// The change is applied right away to documents and queries
+/// analysed in this language. If `reindex` is true, all documents
+/// analysed in this language are indexed again in the background, as
+/// any of them may hold the removed stop words.
+///
+/// # Errors
+///
+/// If the language has no pipeline, a 404 error is returned. Other
+/// errors might originate from the database, Diesel, or Rocket
+#[delete("/stopwords/<lang>?<reindex>", data = "<words>")]
+pub fn remove_stopwords(
+    lang: &str,
+    reindex: Option<bool>,
+    words: Json<Vec<String>>,
+    state: &State<ServerState>,
+    _auth: UserSession<'_>,
+) -> ApiResponse<Json<StopwordsUpdate>> {
+    info!("Removing stop words from {lang}");
+    let language = stopwords_language(state, lang)?;
+    let words = normalize_words(&words.into_inner());
+    let conn = &mut get_connector!(state);
+    let changed = db::update_stopwords(conn, &language, &words, true)
+        .map_err(|e| api_error!(e.to_string()))?;
+    load_stopwords(conn, state)?;
+    let documents = if reindex.unwrap_or(false) && !changed.is_empty() {
+        db::list_documents(conn)
+            .map_err(|e| api_error!(e.to_string()))?
+            .into_iter()
+            .filter(|document| {
+                state.languages.resolve(document.language.as_deref())
+                    == language
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+    Ok(Json(StopwordsUpdate {
+        changed,
+        reindexing: reindex_in_background(state, documents),
+    }))
+}
+
+// Synonyms ///////////////////////////////////////////////////////////////////
+
+/// Reload synonyms from the database
+///
+/// Return the synonym groups now in use.
+fn load_synonyms(
+    conn: &mut DbPool,
+    state: &ServerState,
+) -> ApiResponse<Vec<SynonymGroup>> {
+    let groups =
+        db::list_synonyms(conn).map_err(|e| api_error!(e.to_string()))?;
+    lock!(state.synonyms.write(), "Synonyms")?.load(&groups, &state.languages);
+    Ok(groups)
+}
+
+/// Check and normalize a synonym group sent by the user
+///
+/// The language of the group is normalized, and words are trimmed and
+/// lowercased, empty ones being left out.
+///
+/// # Errors
+///
+/// If the language is not a valid language tag, or if the group is
+/// left without any word, return a 400 error.
+fn normalize_synonyms(changes: SynonymChanges) -> ApiResponse<SynonymChanges> {
+    let words = |words: Vec<String>| {
+        words
+            .iter()
+            .map(|word| word.trim().to_lowercase())
+            .filter(|word| !word.is_empty())
+            .collect::<Vec<String>>()
+    };
+    let language = changes
+        .language
+        .map(|tag| {
+            language::normalize_tag(&tag).ok_or_else(|| {
+                Custom(Status::BadRequest, format!("Invalid language {tag}"))
+            })
+        })
+        .transpose()?;
+    let changes = SynonymChanges {
+        language,
+        words: changes.words.map(words),
+        expansions: changes.expansions.map(words),
+    };
+    if matches!(&changes.words, Some(words) if words.is_empty()) {
+        return Err(Custom(
+            Status::BadRequest,
+            "A synonym group needs at least one word".to_string(),
+        ));
+    }
+    Ok(changes)
+}
+
+/// List synonym groups
+///
+/// # Errors
+///
+/// Errors might originate from the database, Diesel, or Rocket
+#[get("/synonyms")]
+pub fn list_synonyms(
+    state: &State<ServerState>,
+    _auth: UserSession<'_>,
+) -> ApiResponse<Json<Vec<SynonymGroup>>> {
+    info!("Listing synonyms");
+    let conn = &mut get_connector!(state);
+    json_val_or_error!(db::list_synonyms(conn))
+}
+
+/// Create a synonym group
+///
+/// The group needs at least one word. Its language defaults to the
+/// default language of the indexer. Synonyms are reloaded right away.
+/// Return the created group.
+///
+/// # Errors
+///
+/// If the group is invalid, a 400 error is returned. Other errors
+/// might originate from the database, Diesel, or Rocket
+#[post("/synonyms", data = "<group>")]
+pub fn add_synonyms(
+    group: Json<SynonymChanges>,
+    state: &State<ServerState>,
+    _auth: UserSession<'_>,
+) -> ApiResponse<Json<SynonymGroup>> {
+    info!("Adding synonyms");
+    let mut group = normalize_synonyms(group.into_inner())?;
+    if group.words.is_none() {
+        return Err(Custom(
+            Status::BadRequest,
+            "A synonym group needs at least one word".to_string(),
+        ));
+    }
+    group.language = group
+        .language
+        .or_else(|| Some(state.languages.default_language().to_string()));
+    let conn = &mut get_connector!(state);
+    let group = db::add_synonyms(conn, &group)
+        .map_err(|e| api_error!(e.to_string()))?;
+    load_synonyms(conn, state)?;
+    Ok(Json(group))
+}
+
+/// Update the synonym group `id`
+///
+/// Fields which are not set are left untouched. Synonyms are reloaded
+/// right away. Return the updated group.
+///
+/// # Errors
+///
+/// If the group does not exist, a 404 error is returned, and if the
+/// changes are invalid, a 400 error is returned. Other errors might
+/// originate from the database, Diesel, or Rocket
+#[patch("/synonyms/<id>", data = "<changes>")]
+pub fn update_synonyms(
+    id: i32,
+    changes: Json<SynonymChanges>,
+    state: &State<ServerState>,
+    _auth: UserSession<'_>,
+) -> ApiResponse<Json<SynonymGroup>> {
+    info!("Updating synonyms {id}");
+    let changes = normalize_synonyms(changes.into_inner())?;
+    let conn = &mut get_connector!(state);
+    match db::update_synonyms(conn, id, &changes) {
+        Ok(group) => {
+            load_synonyms(conn, state)?;
+            Ok(Json(group))
+        }
+        Err(diesel::NotFound) => Err(Custom(
+            Status::NotFound,
+            format!("Synonym group {id} not found"),
+        )),
+        Err(e) => Err(api_error!(e.to_string())),
+    }
+}
+
+/// Delete the synonym group `id`
+///
+/// Synonyms are reloaded right away.
+///
+/// # Errors
+///
+/// If the group does not exist, a 404 error is returned. Other errors
+/// might originate from the database, Diesel, or Rocket
+#[delete("/synonyms/<id>")]
+pub fn delete_synonyms(
+    id: i32,
+    state: &State<ServerState>,
+    _auth: UserSession<'_>,
+) -> ApiResponse<()> {
+    info!("Deleting synonyms {id}");
+    let conn = &mut get_connector!(state);
+    match db::delete_synonyms(conn, id) {
+        Ok(()) => load_synonyms(conn, state).map(|_| ()),
+        Err(diesel::NotFound) => Err(Custom(
+            Status::NotFound,
+            format!("Synonym group {id} not found"),
+        )),
+        Err(e) => Err(api_error!(e.to_string())),
+    }
+}
+
+/// Reload synonyms from the database
+///
+/// Synonyms are reloaded whenever they are changed through the API,
+/// but they must be reloaded by hand when the database is edited
+/// directly. Return the synonym groups now in use.
+///
+/// # Errors
+///
+/// Errors might originate from the database, Diesel, or Rocket
+#[post("/synonyms/reload")]
+pub fn reload_synonyms(
+    state: &State<ServerState>,
+    _auth: UserSession<'_>,
+) -> ApiResponse<Json<Vec<SynonymGroup>>> {
+    info!("Reloading synonyms");
+    let conn = &mut get_connector!(state);
+    load_synonyms(conn, state).map(Json)
+}
+
+/// Train the spelling dictionaries on the vocabulary of indexed
+/// documents
+///
+/// The words of the documents of each language and their number of
+/// occurrences are added to the corpus dictionary of the language, see
+/// [`Languages::train_dictionaries`]. Return the number of words of
+/// the dictionary of each language.
+///
+/// The vocabulary is read and the dictionaries are built on a blocking
+/// thread, so the async runtime is not held up.
+///
+/// # Errors
+///
+/// Errors might originate from the database, Diesel, or Rocket
+///
+/// [`Languages::train_dictionaries`]: ../language/struct.Languages.html#method.train_dictionaries
+#[post("/spelling/train")]
+pub async fn train_spelling(
+    state: &State<ServerState>,
+    _auth: UserSession<'_>,
+) -> ApiResponse<Json<HashMap<String, usize>>> {
+    info!("Training spelling dictionaries");
+    let pool = state.pool.clone();
+    let languages = Arc::clone(&state.languages);
+    rocket::tokio::task::spawn_blocking(move || {
+        let conn = &mut pool.get().map_err(simple_internal_error)?;
+        let vocabulary =
+            db::vocabulary(conn).map_err(|e| api_error!(e.to_string()))?;
+        Ok(Json(languages.train_dictionaries(vocabulary)))
+    })
+    .await
+    .map_err(simple_internal_error)?
 }
 
 // Utilities //////////////////////////////////////////////////////////////////
-#[get("/spelling/<word>")]
+/// Suggest corrections of the spelling of `word`
+///
+/// Return the `count` most probable corrections, five by default,
+/// along with their edit distance and probability. `word` itself
+/// comes first if it is in the dictionary and is the most probable.
+/// The dictionary of the language `lang` is used if given, otherwise
+/// the one of the language detected from the word.
+#[get("/spelling/<word>?<lang>&<count>")]
 #[must_use]
-pub fn spelling_word(word: String, state: &State<ServerState>) -> String {
-    crate::spelling::correct(word, &state.dictionary)
+pub fn spelling_word(
+    word: &str,
+    lang: Option<&str>,
+    count: Option<usize>,
+    state: &State<ServerState>,
+) -> Json<Vec<Suggestion>> {
+    let language = state.languages.query_language(lang, word);
+    let pipeline = state.languages.pipeline(Some(&language));
+    let word = word.to_lowercase();
+    Json(pipeline.dictionary().map_or_else(Vec::new, |dictionary| {
+        dictionary.suggestions(&word, count.unwrap_or(SUGGESTIONS))
+    }))
 }
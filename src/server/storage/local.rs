@@ -0,0 +1,78 @@
+use std::path::{Component, Path, PathBuf};
+
+use rocket::tokio::fs;
+
+use super::{Storage, StorageError, StorageResult};
+
+/// Storage backend keeping files in a local directory
+///
+/// Files are served by the indexer itself.
+#[allow(clippy::module_name_repetitions)]
+pub struct LocalStorage {
+    root: PathBuf,
+}
+
+impl LocalStorage {
+    /// Store files in the directory `root`, creating it if needed
+    ///
+    /// # Errors
+    ///
+    /// If `root` does not exist and cannot be created, the error is
+    /// returned to the caller.
+    pub fn new(root: PathBuf) -> StorageResult<Self> {
+        std::fs::create_dir_all(&root).map_err(|e| {
+            StorageError(format!(
+                "Failed to create storage directory {}: {e}",
+                root.display()
+            ))
+        })?;
+        Ok(Self { root })
+    }
+
+    /// Path of the file `filename` in the storage directory
+    ///
+    /// Reject any filename which is not a plain filename in order to
+    /// prevent escaping the storage directory.
+    fn path(&self, filename: &str) -> StorageResult<PathBuf> {
+        let mut components = Path::new(filename).components();
+        match (components.next(), components.next()) {
+            (Some(Component::Normal(name)), None) => Ok(self.root.join(name)),
+            _ => Err(StorageError(format!("Invalid filename {filename}"))),
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl Storage for LocalStorage {
+    async fn upload(&self, filename: &str, path: &Path) -> StorageResult<()> {
+        let destination = self.path(filename)?;
+        fs::copy(path, &destination).await.map_err(|e| {
+            StorageError(format!(
+                "Failed to write {}: {e}",
+                destination.display()
+            ))
+        })?;
+        info!("Stored file {}", destination.display());
+        Ok(())
+    }
+
+    async fn get(&self, filename: &str) -> StorageResult<Vec<u8>> {
+        let path = self.path(filename)?;
+        fs::read(&path).await.map_err(|e| {
+            StorageError(format!("Failed to read {}: {e}", path.display()))
+        })
+    }
+
+    async fn delete(&self, filename: &str) -> StorageResult<()> {
+        let path = self.path(filename)?;
+        fs::remove_file(&path).await.map_err(|e| {
+            StorageError(format!("Failed to remove {}: {e}", path.display()))
+        })?;
+        info!("Removed file {}", path.display());
+        Ok(())
+    }
+
+    fn url(&self, _filename: &str) -> Option<String> {
+        None
+    }
+}
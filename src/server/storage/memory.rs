@@ -0,0 +1,53 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::RwLock;
+
+use super::{Storage, StorageError, StorageResult};
+
+/// Storage backend keeping files in memory
+///
+/// Files are served by the indexer itself and are lost when the
+/// server stops, which makes this backend only useful for tests and
+/// throwaway deployments.
+#[derive(Default)]
+#[allow(clippy::module_name_repetitions)]
+pub struct MemoryStorage {
+    files: RwLock<HashMap<String, Vec<u8>>>,
+}
+
+impl MemoryStorage {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[rocket::async_trait]
+impl Storage for MemoryStorage {
+    async fn upload(&self, filename: &str, path: &Path) -> StorageResult<()> {
+        let file = rocket::tokio::fs::read(path).await.map_err(|e| {
+            StorageError(format!("Failed to read {}: {e}", path.display()))
+        })?;
+        lock!(self.files.write(), "Storage", StorageError)?
+            .insert(filename.to_string(), file);
+        Ok(())
+    }
+
+    async fn get(&self, filename: &str) -> StorageResult<Vec<u8>> {
+        lock!(self.files.read(), "Storage", StorageError)?
+            .get(filename)
+            .cloned()
+            .ok_or_else(|| StorageError(format!("File {filename} not found")))
+    }
+
+    async fn delete(&self, filename: &str) -> StorageResult<()> {
+        lock!(self.files.write(), "Storage", StorageError)?
+            .remove(filename)
+            .map(|_| ())
+            .ok_or_else(|| StorageError(format!("File {filename} not found")))
+    }
+
+    fn url(&self, _filename: &str) -> Option<String> {
+        None
+    }
+}
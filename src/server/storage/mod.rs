@@ -0,0 +1,150 @@
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+pub mod local;
+pub mod memory;
+pub mod s3;
+
+pub use local::LocalStorage;
+pub use memory::MemoryStorage;
+pub use s3::S3Storage;
+
+#[allow(clippy::module_name_repetitions)]
+#[derive(Debug)]
+pub struct StorageError(String);
+
+impl StorageError {
+    pub fn new<E>(error: E) -> Self
+    where
+        E: Display,
+    {
+        Self(error.to_string())
+    }
+}
+
+impl Display for StorageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[allow(clippy::module_name_repetitions)]
+pub type StorageResult<T> = Result<T, StorageError>;
+
+/// Storage backend in which offline documents are kept
+///
+/// Offline documents are uploaded by users and must be stored
+/// somewhere in order to be downloaded later. Each implementation of
+/// this trait stores documents by their filename, which is also their
+/// name in the database.
+#[rocket::async_trait]
+pub trait Storage: Send + Sync {
+    /// Store the file located at `path` under the name `filename`
+    ///
+    /// # Errors
+    ///
+    /// If the backend fails to read or store the file, the error is
+    /// returned to the caller.
+    async fn upload(&self, filename: &str, path: &Path) -> StorageResult<()>;
+
+    /// Retrieve the content of the file stored as `filename`
+    ///
+    /// # Errors
+    ///
+    /// If the file does not exist or cannot be read, the error is
+    /// returned to the caller.
+    async fn get(&self, filename: &str) -> StorageResult<Vec<u8>>;
+
+    /// Remove the file stored as `filename`
+    ///
+    /// # Errors
+    ///
+    /// If the backend fails to remove the file, the error is returned
+    /// to the caller.
+    async fn delete(&self, filename: &str) -> StorageResult<()>;
+
+    /// URL at which the file stored as `filename` can be downloaded
+    /// directly, if the backend exposes its files publicly
+    ///
+    /// Files without a public URL are downloaded through the
+    /// indexer’s `/docs/<id>/download` endpoint.
+    fn url(&self, filename: &str) -> Option<String>;
+
+    /// URL to which a download of the file `filename` is redirected,
+    /// if any
+    ///
+    /// Unlike [`Storage::url`], this URL may be short-lived. By
+    /// default, it is the same URL as [`Storage::url`]. Files without
+    /// one are served by the indexer itself, see [`Storage::get`].
+    ///
+    /// # Errors
+    ///
+    /// If the backend fails to generate the URL, the error is returned
+    /// to the caller.
+    fn download_url(&self, filename: &str) -> StorageResult<Option<String>> {
+        Ok(self.url(filename))
+    }
+}
+
+/// Kind of storage backend the server can use
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    S3,
+    Local,
+    Memory,
+}
+
+impl FromStr for Backend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "s3" => Ok(Self::S3),
+            "local" => Ok(Self::Local),
+            "memory" => Ok(Self::Memory),
+            other => Err(format!("Unknown storage backend {other}")),
+        }
+    }
+}
+
+/// Create the storage backend `backend`
+///
+/// The S3 backend is configured through the `S3_BUCKET_ID`,
+/// `S3_REGION`, and `S3_ENDPOINT` environment variables. Files stored
+/// locally are stored in the directory `path`.
+///
+/// If `presign_expiry` is set, files stored in a S3 bucket are
+/// downloaded through presigned URLs valid for `presign_expiry`
+/// seconds, which allows the bucket to stay private.
+///
+/// # Errors
+///
+/// If the S3 environment variables are not set, if the connection to
+/// the bucket cannot be configured, or if the local directory cannot
+/// be created, an error is returned.
+pub fn make_storage(
+    backend: Backend,
+    path: PathBuf,
+    presign_expiry: Option<u32>,
+) -> StorageResult<Box<dyn Storage>> {
+    let env = |name: &str| {
+        std::env::var(name)
+            .map_err(|_| StorageError(format!("{name} must be set!")))
+    };
+    Ok(match backend {
+        Backend::S3 => {
+            let storage = S3Storage::new(
+                &env("S3_BUCKET_ID")?,
+                env("S3_REGION")?,
+                env("S3_ENDPOINT")?,
+            )?;
+            Box::new(match presign_expiry {
+                Some(expiry) => storage.with_presigned_urls(expiry),
+                None => storage,
+            })
+        }
+        Backend::Local => Box::new(LocalStorage::new(path)?),
+        Backend::Memory => Box::new(MemoryStorage::new()),
+    })
+}
@@ -0,0 +1,148 @@
+use std::path::Path;
+
+use rocket::tokio::fs::File;
+
+use super::{Storage, StorageError, StorageResult};
+
+/// Storage backend keeping files in a S3 bucket
+///
+/// By default, files are downloaded directly from the bucket, which
+/// must then be publicly readable. See
+/// [`S3Storage::with_presigned_urls`] to keep the bucket private.
+#[allow(clippy::module_name_repetitions)]
+pub struct S3Storage {
+    bucket: s3::Bucket,
+    endpoint: String,
+    /// Lifetime in seconds of presigned download URLs, if files are
+    /// downloaded through them
+    presign_expiry: Option<u32>,
+}
+
+impl S3Storage {
+    /// Connect to a S3 bucket already existing
+    ///
+    /// The bucket must have the ID `name`, be set in the region
+    /// `region` and be available at `endpoint`.
+    ///
+    /// # Errors
+    ///
+    /// The creation of credentials is not guaranteed and may error
+    /// out, in which case the error is returned to the caller of the
+    /// function. The variables `AWS_ACCESS_KEY_ID`,
+    /// `AWS_SECRET_ACCESS_KEY`, and `AWS_SESSION_TOKEN` must be set,
+    /// regardless if the S3 bucket is hosted by Amazon or not.
+    pub fn new(
+        name: &str,
+        region: String,
+        endpoint: String,
+    ) -> StorageResult<Self> {
+        let credentials =
+            s3::creds::Credentials::default().map_err(StorageError::new)?;
+        let bucket = s3::Bucket::new(
+            name,
+            s3::region::Region::Custom {
+                region,
+                endpoint: endpoint.clone(),
+            },
+            credentials,
+        )
+        .map_err(StorageError::new)?;
+        Ok(Self {
+            bucket,
+            endpoint,
+            presign_expiry: None,
+        })
+    }
+
+    /// Download files through presigned URLs
+    ///
+    /// Presigned URLs are valid for `expiry` seconds. Since they
+    /// expire, files have no public URL and documents link to the
+    /// indexer’s `/docs/<id>/download` endpoint instead, which
+    /// redirects to a freshly presigned URL.
+    #[must_use]
+    pub fn with_presigned_urls(self, expiry: u32) -> Self {
+        Self {
+            presign_expiry: Some(expiry),
+            ..self
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl Storage for S3Storage {
+    /// Stream the file to the bucket
+    ///
+    /// Files larger than a single chunk are sent through a multipart
+    /// upload, so they are never fully loaded in memory.
+    async fn upload(&self, filename: &str, path: &Path) -> StorageResult<()> {
+        let mut file = File::open(path).await.map_err(|e| {
+            StorageError(format!("Failed to read {}: {e}", path.display()))
+        })?;
+        self.bucket
+            .put_object_stream(&mut file, format!("/{filename}"))
+            .await
+            .map(|_| info!("Uploaded file!"))
+            .map_err(|e| {
+                info!("Failed to upload file: {e}");
+                StorageError(format!("Failed to upload file: {e}"))
+            })
+    }
+
+    async fn get(&self, filename: &str) -> StorageResult<Vec<u8>> {
+        self.bucket
+            .get_object(format!("/{filename}"))
+            .await
+            .map(|response| response.bytes().to_vec())
+            .map_err(|e| {
+                StorageError(format!(
+                    "Failed to retrieve remote object {filename} from S3 bucket {}: {e}",
+                    self.bucket.name()
+                ))
+            })
+    }
+
+    async fn delete(&self, filename: &str) -> StorageResult<()> {
+        self.bucket
+            .delete_object(format!("/{filename}"))
+            .await
+            .map(|_| {
+                info!(
+                    "Removed remote object {filename} from S3 bucket {}",
+                    self.bucket.name()
+                );
+            })
+            .map_err(|e| {
+                StorageError(format!(
+                    "Failed to remove remote object {filename} from S3 bucket {}: {e}",
+                    self.bucket.name()
+                ))
+            })
+    }
+
+    fn url(&self, filename: &str) -> Option<String> {
+        match self.presign_expiry {
+            Some(_) => None,
+            None => Some(format!(
+                "https://{}.{}/{filename}",
+                self.bucket.name(),
+                self.endpoint
+            )),
+        }
+    }
+
+    fn download_url(&self, filename: &str) -> StorageResult<Option<String>> {
+        match self.presign_expiry {
+            Some(expiry) => self
+                .bucket
+                .presign_get(format!("/{filename}"), expiry, None)
+                .map(Some)
+                .map_err(|e| {
+                    StorageError(format!(
+                        "Failed to presign URL for {filename}: {e}"
+                    ))
+                }),
+            None => Ok(self.url(filename)),
+        }
+    }
+}
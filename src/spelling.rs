@@ -1,16 +1,485 @@
 use color_eyre::eyre::Result;
 use rocket::serde::{Deserialize, Serialize};
-use std::{collections::HashMap, path::PathBuf};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
 
-/// All characters used in the languages stored in the database
-pub static ALPHABET: &str = "aàâbcdeéèëêfghiîïjklmnoôpqrstuûüvwxyÿz";
+/// Largest edit distance of the corrections of a word
+pub const MAX_DISTANCE: usize = 2;
 
-#[derive(Serialize, Deserialize, Debug)]
+/// Probability that a character of a word is mistyped, deleted,
+/// inserted, or transposed, used to weigh corrections by their edit
+/// distance
+const ERROR_RATE: f64 = 0.05;
+
+/// Number of occurrences given to a word missing from the dictionary,
+/// so that it may still be right when its corrections are rare
+const UNKNOWN_OCCURRENCES: f64 = 0.5;
+
+/// Weight of the number of occurrences of pairs of words relative to
+/// the number of occurrences of single words, when correcting words
+/// in context
+const BIGRAM_WEIGHT: f64 = 0.8;
+
+/// Number of candidate corrections of each word kept when correcting
+/// words in context
+const PHRASE_CANDIDATES: usize = 10;
+
+/// Number of characters at the start of words whose deletes are
+/// indexed, see [`Dictionary::edits`]
+///
+/// [`Dictionary::edits`]: ./struct.Dictionary.html#structfield.edits
+const PREFIX_LENGTH: usize = 7;
+
+/// Dictionary of words and their number of occurrences, used to
+/// correct the spelling of words
+///
+/// Corrections are looked up with the symmetric delete algorithm of
+/// `SymSpell`: the words which can be obtained by deleting up to
+/// [`MAX_DISTANCE`] characters from each word of the dictionary are
+/// indexed when the dictionary is created. Deleting characters from a
+/// misspelled word then finds its corrections among the indexed
+/// deletes, without generating every possible edit of the word.
+///
+/// [`MAX_DISTANCE`]: ./constant.MAX_DISTANCE.html
+#[derive(Serialize, Deserialize, Debug, Default)]
 #[serde(crate = "rocket::serde")]
 pub struct Dictionary {
     pub n: usize,
     pub words: HashMap<String, usize>,
-    pub edits: Vec<String>,
+    /// Words of the dictionary, sorted, referred to by `edits`
+    pub vocabulary: Vec<String>,
+    /// Deletes of the first [`PREFIX_LENGTH`] characters of each word,
+    /// as pairs of the hash of a delete and of the index of the word
+    /// in `vocabulary`, sorted by hash
+    ///
+    /// [`PREFIX_LENGTH`]: ./constant.PREFIX_LENGTH.html
+    pub edits: Vec<(u32, u32)>,
+    /// Number of occurrences of pairs of consecutive words, as both
+    /// words separated by a space
+    pub bigrams: HashMap<String, usize>,
+}
+
+/// Correction of the words of a phrase, see
+/// [`Dictionary::correct_phrase`]
+///
+/// [`Dictionary::correct_phrase`]: ./struct.Dictionary.html#method.correct_phrase
+#[derive(Debug, Clone, PartialEq)]
+pub struct Correction {
+    /// Corrected words, which may be more or fewer than the words of
+    /// the phrase when words are split or merged
+    pub words: Vec<String>,
+    /// Probability that the phrase was meant to be its correction
+    pub confidence: f64,
+}
+
+/// Best and total probabilities, as logarithms, of the corrections of
+/// the start of a phrase ending with a given word, along with the
+/// correction preceding the best one
+struct State {
+    best: f64,
+    total: f64,
+    previous: Option<(usize, String, Vec<String>)>,
+}
+
+/// Candidate correction of a word
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(crate = "rocket::serde")]
+pub struct Suggestion {
+    pub word: String,
+    /// Edit distance between the word and its correction, see
+    /// [`Dictionary::lookup`]
+    ///
+    /// [`Dictionary::lookup`]: ./struct.Dictionary.html#method.lookup
+    pub distance: usize,
+    /// Probability that the word was meant to be its correction
+    pub probability: f64,
+}
+
+impl Dictionary {
+    /// Create a dictionary from `words` and `bigrams` and their number
+    /// of occurrences, and index the deletes of `words`
+    ///
+    /// # Panics
+    ///
+    /// The program panics if there are more than `u32::MAX` words.
+    #[must_use]
+    #[allow(clippy::implicit_hasher)]
+    pub fn new(
+        words: HashMap<String, usize>,
+        bigrams: HashMap<String, usize>,
+    ) -> Self {
+        let mut vocabulary: Vec<String> = words.keys().cloned().collect();
+        vocabulary.sort_unstable();
+        let mut edits: Vec<(u32, u32)> = vocabulary
+            .iter()
+            .enumerate()
+            .flat_map(|(index, word)| {
+                let index = u32::try_from(index).expect("too many words");
+                deletes(prefix(word), MAX_DISTANCE)
+                    .into_iter()
+                    .map(move |delete| (hash(&delete), index))
+            })
+            .collect();
+        edits.sort_unstable();
+        edits.dedup();
+        Self {
+            n: words.values().sum(),
+            words,
+            vocabulary,
+            edits,
+            bigrams,
+        }
+    }
+
+    /// Find the words of the dictionary at most `max_distance` edits
+    /// away from `word`, along with their distance
+    ///
+    /// The distance is the number of insertions, deletions,
+    /// substitutions, and transpositions of adjacent characters
+    /// turning `word` into one of the dictionary. Words are returned in
+    /// no particular order.
+    #[must_use]
+    pub fn lookup(
+        &self,
+        word: &str,
+        max_distance: usize,
+    ) -> Vec<(&str, usize)> {
+        let max_distance = max_distance.min(MAX_DISTANCE);
+        let length = word.chars().count();
+        let mut seen: HashSet<u32> = HashSet::new();
+        let mut candidates = Vec::new();
+        for delete in deletes(prefix(word), max_distance) {
+            let hash = hash(&delete);
+            let start = self.edits.partition_point(|&(h, _)| h < hash);
+            for &(_, index) in
+                self.edits[start..].iter().take_while(|&&(h, _)| h == hash)
+            {
+                if !seen.insert(index) {
+                    continue;
+                }
+                let Some(candidate) = self.vocabulary.get(index as usize)
+                else {
+                    continue;
+                };
+                if candidate.chars().count().abs_diff(length) > max_distance {
+                    continue;
+                }
+                let distance = distance(word, candidate);
+                if distance <= max_distance {
+                    candidates.push((candidate.as_str(), distance));
+                }
+            }
+        }
+        candidates
+    }
+
+    /// Rank the candidate corrections of `word`, and return the
+    /// `count` most probable ones
+    ///
+    /// Each correction is weighed by its number of occurrences and by
+    /// [`ERROR_RATE`] to the power of its edit distance. Its
+    /// probability is its share of the weights of all corrections and
+    /// of `word` itself. `word` is a correction of its own if it is in
+    /// the dictionary, otherwise it weighs [`UNKNOWN_OCCURRENCES`] but
+    /// is not returned, so probabilities sum up to less than one.
+    /// Candidates missing from the words of the dictionary, which may
+    /// only be found in a corrupted dictionary, are skipped.
+    ///
+    /// [`ERROR_RATE`]: ./constant.ERROR_RATE.html
+    /// [`UNKNOWN_OCCURRENCES`]: ./constant.UNKNOWN_OCCURRENCES.html
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn suggestions(&self, word: &str, count: usize) -> Vec<Suggestion> {
+        let mut candidates: Vec<(&str, usize, f64)> = self
+            .lookup(word, MAX_DISTANCE)
+            .into_iter()
+            .filter_map(|(candidate, distance)| {
+                // The vocabulary of a corrupted dictionary may not match
+                // its words
+                let occurrences = *self.words.get(candidate)? as f64;
+                let weight = occurrences
+                    * ERROR_RATE
+                        .powi(i32::try_from(distance).unwrap_or(i32::MAX));
+                Some((candidate, distance, weight))
+            })
+            .collect();
+        let total = candidates.iter().map(|(_, _, weight)| weight).sum::<f64>()
+            + if self.words.contains_key(word) {
+                0.0
+            } else {
+                UNKNOWN_OCCURRENCES
+            };
+        candidates.sort_by(|(a, _, a_weight), (b, _, b_weight)| {
+            b_weight.total_cmp(a_weight).then_with(|| a.cmp(b))
+        });
+        candidates
+            .into_iter()
+            .take(count)
+            .map(|(candidate, distance, weight)| Suggestion {
+                word: candidate.to_string(),
+                distance,
+                probability: weight / total,
+            })
+            .collect()
+    }
+
+    /// Number of occurrences of `word`, or [`UNKNOWN_OCCURRENCES`] if
+    /// it is not in the dictionary
+    ///
+    /// [`UNKNOWN_OCCURRENCES`]: ./constant.UNKNOWN_OCCURRENCES.html
+    #[allow(clippy::cast_precision_loss)]
+    fn occurrences(&self, word: &str) -> f64 {
+        self.words
+            .get(word)
+            .map_or(UNKNOWN_OCCURRENCES, |&occurrences| occurrences as f64)
+    }
+
+    /// Probability of `word` following the word `previous`, if any
+    ///
+    /// The probability of `word` alone is interpolated with the
+    /// probability of `word` following `previous` in the pairs of
+    /// words of the dictionary, which weighs [`BIGRAM_WEIGHT`]. The
+    /// latter is zero when `previous` is not in the dictionary.
+    ///
+    /// [`BIGRAM_WEIGHT`]: ./constant.BIGRAM_WEIGHT.html
+    #[allow(clippy::cast_precision_loss)]
+    fn probability(&self, previous: Option<&str>, word: &str) -> f64 {
+        let unigram = self.occurrences(word) / self.n.max(1) as f64;
+        let Some(previous) = previous else {
+            return unigram;
+        };
+        let bigram = match self.words.get(previous) {
+            Some(&occurrences) => {
+                self.bigrams
+                    .get(&format!("{previous} {word}"))
+                    .map_or(0.0, |&bigram| bigram as f64)
+                    / occurrences.max(1) as f64
+            }
+            None => 0.0,
+        };
+        BIGRAM_WEIGHT * bigram + (1.0 - BIGRAM_WEIGHT) * unigram
+    }
+
+    /// Candidate corrections of `word` in a phrase, along with the
+    /// probability of their edits
+    ///
+    /// Words made of other characters than letters, such as numbers,
+    /// are left as is. Otherwise, candidates are the corrections of
+    /// `word` (see [`Dictionary::lookup`]), and if it is not in the
+    /// dictionary, `word` itself and the pairs of words it may be
+    /// split into. Only the [`PHRASE_CANDIDATES`] most probable ones
+    /// are kept.
+    ///
+    /// [`Dictionary::lookup`]: ./struct.Dictionary.html#method.lookup
+    /// [`PHRASE_CANDIDATES`]: ./constant.PHRASE_CANDIDATES.html
+    fn candidates(&self, word: &str) -> Vec<(Vec<String>, f64)> {
+        if !word.chars().all(char::is_alphabetic) {
+            return vec![(vec![word.to_string()], 1.0)];
+        }
+        let mut candidates: Vec<(Vec<String>, f64)> = self
+            .lookup(word, MAX_DISTANCE)
+            .into_iter()
+            .map(|(candidate, distance)| {
+                let edits = i32::try_from(distance).unwrap_or(i32::MAX);
+                (vec![candidate.to_string()], ERROR_RATE.powi(edits))
+            })
+            .collect();
+        if !self.words.contains_key(word) {
+            candidates.push((vec![word.to_string()], 1.0));
+            for (index, _) in word.char_indices().skip(1) {
+                let (first, second) = word.split_at(index);
+                if self.words.contains_key(first)
+                    && self.words.contains_key(second)
+                {
+                    candidates.push((
+                        vec![first.to_string(), second.to_string()],
+                        ERROR_RATE,
+                    ));
+                }
+            }
+        }
+        let weight = |(words, edits): &(Vec<String>, f64)| {
+            edits * words.iter().map(|w| self.occurrences(w)).product::<f64>()
+        };
+        candidates.sort_by(|a, b| weight(b).total_cmp(&weight(a)));
+        candidates.truncate(PHRASE_CANDIDATES);
+        candidates
+    }
+
+    /// Correct the spelling of the words of a phrase in context
+    ///
+    /// Each word may be replaced by one of its candidate corrections
+    /// (see [`Dictionary::candidates`]), and two consecutive words may
+    /// be merged into one of the dictionary. The correction chosen is
+    /// the most probable one according to the probabilities of the
+    /// edits and of each word following the previous one (see
+    /// [`Dictionary::probability`]), so that `pomme de tere` is
+    /// corrected to `pomme de terre` rather than to a more frequent
+    /// word. Its confidence is its share of the probabilities of all
+    /// the corrections of the phrase.
+    ///
+    /// [`Dictionary::candidates`]: ./struct.Dictionary.html#method.candidates
+    /// [`Dictionary::probability`]: ./struct.Dictionary.html#method.probability
+    #[must_use]
+    pub fn correct_phrase(&self, words: &[&str]) -> Correction {
+        // lattice[i] holds the corrections of the first i words,
+        // indexed by their last word
+        let mut lattice: Vec<HashMap<String, State>> =
+            words.iter().map(|_| HashMap::new()).collect();
+        lattice.push(HashMap::new());
+        lattice[0].insert(
+            String::new(),
+            State {
+                best: 0.0,
+                total: 0.0,
+                previous: None,
+            },
+        );
+        for (i, word) in words.iter().enumerate() {
+            let mut spans = vec![(i + 1, self.candidates(word))];
+            if let Some(next) = words.get(i + 1) {
+                let merged = format!("{word}{next}");
+                if merged.chars().all(char::is_alphabetic)
+                    && self.words.contains_key(&merged)
+                {
+                    spans.push((i + 2, vec![(vec![merged], ERROR_RATE)]));
+                }
+            }
+            let states: Vec<(String, f64, f64)> = lattice[i]
+                .iter()
+                .map(|(last, state)| (last.clone(), state.best, state.total))
+                .collect();
+            for (last, best, total) in states {
+                for (end, candidates) in &spans {
+                    for (candidate, edits) in candidates {
+                        let mut score = edits.ln();
+                        let mut previous =
+                            Some(last.as_str()).filter(|last| !last.is_empty());
+                        for word in candidate {
+                            score += self.probability(previous, word).ln();
+                            previous = Some(word);
+                        }
+                        let state = lattice[*end]
+                            .entry(
+                                candidate.last().cloned().unwrap_or_default(),
+                            )
+                            .or_insert(State {
+                                best: f64::NEG_INFINITY,
+                                total: f64::NEG_INFINITY,
+                                previous: None,
+                            });
+                        state.total = log_add(state.total, total + score);
+                        if best + score > state.best {
+                            state.best = best + score;
+                            state.previous =
+                                Some((i, last.clone(), candidate.clone()));
+                        }
+                    }
+                }
+            }
+        }
+        let end = &lattice[words.len()];
+        let total = end.values().fold(f64::NEG_INFINITY, |total, state| {
+            log_add(total, state.total)
+        });
+        let Some((mut last, best)) = end
+            .iter()
+            .max_by(|(_, a), (_, b)| a.best.total_cmp(&b.best))
+            .map(|(last, state)| (last.clone(), state.best))
+        else {
+            return Correction {
+                words: Vec::new(),
+                confidence: 1.0,
+            };
+        };
+        let mut corrected = Vec::new();
+        let mut position = words.len();
+        while let Some((start, previous, candidate)) =
+            &lattice[position][&last].previous
+        {
+            corrected.splice(0..0, candidate.iter().cloned());
+            position = *start;
+            last = previous.clone();
+        }
+        Correction {
+            words: corrected,
+            confidence: (best - total).exp(),
+        }
+    }
+}
+
+/// Add two probabilities given as logarithms
+fn log_add(a: f64, b: f64) -> f64 {
+    if a == f64::NEG_INFINITY {
+        return b;
+    }
+    let max = a.max(b);
+    max + ((a - max).exp() + (b - max).exp()).ln()
+}
+
+/// First [`PREFIX_LENGTH`] characters of `word`
+///
+/// [`PREFIX_LENGTH`]: ./constant.PREFIX_LENGTH.html
+fn prefix(word: &str) -> &str {
+    word.char_indices()
+        .nth(PREFIX_LENGTH)
+        .map_or(word, |(index, _)| &word[..index])
+}
+
+/// Words obtained by deleting up to `distance` characters from
+/// `word`, including `word` itself
+fn deletes(word: &str, distance: usize) -> HashSet<String> {
+    let mut deletes: HashSet<String> = HashSet::from([word.to_string()]);
+    let mut current = vec![word.to_string()];
+    for _ in 0..distance {
+        let mut next = Vec::new();
+        for word in &current {
+            for (index, c) in word.char_indices() {
+                let mut delete = word.clone();
+                delete.replace_range(index..index + c.len_utf8(), "");
+                if deletes.insert(delete.clone()) {
+                    next.push(delete);
+                }
+            }
+        }
+        current = next;
+    }
+    deletes
+}
+
+/// Hash of a delete, stable across platforms and versions of Rust
+fn hash(delete: &str) -> u32 {
+    delete.bytes().fold(0x811c_9dc5, |hash, byte| {
+        (hash ^ u32::from(byte)).wrapping_mul(0x0100_0193)
+    })
+}
+
+/// Edit distance between `a` and `b`, counting insertions,
+/// deletions, substitutions, and transpositions of adjacent
+/// characters
+fn distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut rows = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in rows[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = (rows[i - 1][j] + 1)
+                .min(rows[i][j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = distance;
+        }
+    }
+    rows[a.len()][b.len()]
 }
 
 /// Read a dictionary from a binary file
@@ -20,11 +489,6 @@ pub struct Dictionary {
 /// If the function fails to read the file or if it fails to
 /// deserialize its content, the error is returned to the caller of
 /// `read_dictionary`.
-///
-/// # Panics
-///
-/// If a dictionary is provided but the file is not readable or not
-/// deserializable, the program will panic.
 pub fn read_dictionary(path: Option<PathBuf>) -> Result<Option<Dictionary>> {
     match path {
         None => Ok(None),
@@ -35,80 +499,124 @@ pub fn read_dictionary(path: Option<PathBuf>) -> Result<Option<Dictionary>> {
     }
 }
 
-#[must_use]
-pub fn correct(word: String, dictionary: &Option<Dictionary>) -> String {
-    match dictionary {
-        None => word,
-        Some(dictionary) => {
-            if dictionary.words.contains_key(&word) {
-                return word;
-            };
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-            let mut candidates: HashMap<usize, String> = HashMap::new();
-            let edits_w = edits(&word);
+    fn dictionary(
+        words: &[(&str, usize)],
+        bigrams: &[(&str, usize)],
+    ) -> Dictionary {
+        let counts = |pairs: &[(&str, usize)]| {
+            pairs
+                .iter()
+                .map(|(word, occurrences)| (word.to_string(), *occurrences))
+                .collect()
+        };
+        Dictionary::new(counts(words), counts(bigrams))
+    }
 
-            // Try to find an edit of the current word in the dictionary
-            for edit in &edits_w {
-                if let Some(score) = dictionary.words.get(edit) {
-                    candidates.insert(*score, edit.to_string());
-                }
-            }
-            // Return the most likely word among possible variations
-            if let Some(c) = candidates.iter().max_by_key(|&e| e.0) {
-                return c.1.to_string();
-            }
+    fn french() -> Dictionary {
+        dictionary(
+            &[
+                ("pomme", 50),
+                ("de", 1000),
+                ("terre", 100),
+                ("terme", 300),
+                ("portefeuille", 20),
+                ("porte", 80),
+            ],
+            &[("pomme de", 30), ("de terre", 40)],
+        )
+    }
 
-            // Try to find the correct word in the edits_w of the edits_w
-            for edit in &edits_w {
-                for w in edits(edit) {
-                    if let Some(score) = dictionary.words.get(&w) {
-                        candidates.insert(*score, w);
-                    }
-                }
-            }
-            // Again, try to retu
-            if let Some(c) = candidates.iter().max_by_key(|&e| e.0) {
-                return c.1.to_string();
-            }
+    #[test]
+    fn distance_counts_transpositions_once() {
+        assert_eq!(distance("terre", "terre"), 0);
+        assert_eq!(distance("tere", "terre"), 1);
+        assert_eq!(distance("tetre", "terre"), 1);
+        assert_eq!(distance("trere", "terre"), 1);
+        assert_eq!(distance("école", "ecole"), 1);
+        assert_eq!(distance("", "de"), 2);
+    }
 
-            // Can’t find anything, return the word itself
-            word
-        }
+    #[test]
+    fn lookup_finds_words_within_distance() {
+        let dictionary = french();
+        let mut found = dictionary.lookup("tere", MAX_DISTANCE);
+        found.sort_unstable();
+        assert_eq!(found, [("terme", 1), ("terre", 1)]);
+        let mut found = dictionary.lookup("pome", MAX_DISTANCE);
+        found.sort_unstable();
+        assert_eq!(found, [("pomme", 1), ("porte", 2)]);
+        assert_eq!(dictionary.lookup("pome", 1), [("pomme", 1)]);
+        assert!(dictionary.lookup("tere", 0).is_empty());
+        assert!(dictionary.lookup("xyzzy", MAX_DISTANCE).is_empty());
     }
-}
 
-/// Create possible alterations of a word.
-///
-/// Create different kinds of alterations to `word`. It generates
-/// possible character deletion, transposition, alteration or
-/// insertion. The latter two alterations use the letters listed in
-/// [`ALPHABET`].
-///
-/// [`ALPHABET`]: ./static.ALPHABET.html
-#[must_use]
-pub fn edits(word: &str) -> Vec<String> {
-    let mut results: Vec<String> = Vec::new();
-    let word_vec = word.chars().collect::<Vec<_>>();
-    for i in 0..word_vec.len() {
-        let (first, last) = word_vec.split_at(i);
-        // deletion
-        results.push([first, &last[1..]].concat().into_iter().collect());
-        // transposition
-        if i < word_vec.len() - 1 {
-            results.push(
-                [first, &last[1..2], &last[..1], &last[2..]]
-                    .concat()
-                    .into_iter()
-                    .collect(),
-            );
-        }
-        for c in ALPHABET.chars() {
-            // alteration
-            results
-                .push([first, &[c], &last[1..]].concat().into_iter().collect());
-            // insertion
-            results.push([first, &[c], last].concat().into_iter().collect());
-        }
+    #[test]
+    fn lookup_finds_edits_after_the_prefix() {
+        let dictionary = french();
+        let found = dictionary.lookup("portefeuile", MAX_DISTANCE);
+        assert_eq!(found, [("portefeuille", 1)]);
+    }
+
+    #[test]
+    fn suggestions_are_ranked_by_probability() {
+        let suggestions = french().suggestions("tere", 2);
+        let words = suggestions
+            .iter()
+            .map(|suggestion| suggestion.word.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(words, ["terme", "terre"]);
+        assert!(suggestions[0].probability > suggestions[1].probability);
+        let total = suggestions.iter().map(|s| s.probability).sum::<f64>();
+        assert!(total < 1.0);
+    }
+
+    #[test]
+    fn known_words_suggest_themselves() {
+        let suggestions = french().suggestions("terre", 1);
+        assert_eq!(suggestions[0].word, "terre");
+        assert_eq!(suggestions[0].distance, 0);
+        assert!(suggestions[0].probability > 0.8);
+    }
+
+    #[test]
+    fn phrase_is_corrected_in_context() {
+        let correction = french().correct_phrase(&["pomme", "de", "tere"]);
+        assert_eq!(correction.words, ["pomme", "de", "terre"]);
+        assert!(correction.confidence > 0.5);
+    }
+
+    #[test]
+    fn phrase_words_are_split_and_merged() {
+        let dictionary = french();
+        let correction = dictionary.correct_phrase(&["pommede", "terre"]);
+        assert_eq!(correction.words, ["pomme", "de", "terre"]);
+        let correction = dictionary.correct_phrase(&["porte", "feuille"]);
+        assert_eq!(correction.words, ["portefeuille"]);
+    }
+
+    #[test]
+    fn phrase_keeps_numbers() {
+        let correction = french().correct_phrase(&["terre", "2023"]);
+        assert_eq!(correction.words, ["terre", "2023"]);
+    }
+
+    #[test]
+    fn empty_phrase_is_correct() {
+        let correction = french().correct_phrase(&[]);
+        assert!(correction.words.is_empty());
+        assert!((correction.confidence - 1.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn words_missing_from_a_corrupted_dictionary_are_skipped() {
+        let mut dictionary = dictionary(&[("terre", 10), ("serre", 2)], &[]);
+        dictionary.words.remove("serre");
+        let suggestions = dictionary.suggestions("terre", 5);
+        assert_eq!(suggestions.len(), 1);
+        assert_eq!(suggestions[0].word, "terre");
     }
-    results
 }
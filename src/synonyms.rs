@@ -0,0 +1,148 @@
+use std::collections::HashMap;
+
+use tracing::info;
+
+use crate::db::models::SynonymGroup;
+use crate::language::Languages;
+
+/// Weight in percent of the words of a query
+pub const QUERY_WEIGHT: i32 = 100;
+
+/// Query whose terms are expanded with their synonyms
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExpandedQuery {
+    /// Terms of the query itself
+    pub terms: Vec<String>,
+    /// Terms of the query and their synonyms, along with their weight
+    /// in percent
+    pub words: Vec<(String, i32)>,
+}
+
+/// Synonyms of words, by language
+///
+/// Synonyms are stored in the database as [`SynonymGroup`]s and are
+/// loaded into memory, normalized to terms by the pipeline of their
+/// language, see [`Synonyms::load`].
+///
+/// [`SynonymGroup`]: ../db/models/struct.SynonymGroup.html
+/// [`Synonyms::load`]: ./struct.Synonyms.html#method.load
+#[derive(Debug, Default)]
+pub struct Synonyms {
+    /// Weight in percent of synonyms relative to the words of a query
+    weight: i32,
+    expansions: HashMap<String, HashMap<String, Vec<String>>>,
+}
+
+impl Synonyms {
+    /// Create an empty set of synonyms weighing `weight` percent of
+    /// the words of a query
+    #[must_use]
+    pub fn new(weight: i32) -> Self {
+        Self {
+            weight,
+            expansions: HashMap::new(),
+        }
+    }
+
+    /// Replace all synonyms with the ones of `groups`
+    ///
+    /// Words are normalized to their term by the pipeline of the
+    /// group’s language in `languages`, and groups are stored under
+    /// the language of this pipeline, see [`Languages::resolve`].
+    ///
+    /// [`Languages::resolve`]: ../language/struct.Languages.html#method.resolve
+    pub fn load(&mut self, groups: &[SynonymGroup], languages: &Languages) {
+        self.expansions.clear();
+        for group in groups {
+            let resolved = languages.resolve(Some(&group.language));
+            let pipeline = languages.pipeline(Some(resolved));
+            let term = |word: &String| pipeline.analyzer.term(word.trim());
+            let words: Vec<String> = group.words.iter().map(term).collect();
+            let expansions: Vec<String> =
+                group.expansions.iter().map(term).collect();
+            let language =
+                self.expansions.entry(resolved.to_string()).or_default();
+            for word in &words {
+                let synonyms = language.entry(word.clone()).or_default();
+                for synonym in words.iter().chain(&expansions) {
+                    if synonym != word
+                        && !synonym.is_empty()
+                        && !synonyms.contains(synonym)
+                    {
+                        synonyms.push(synonym.clone());
+                    }
+                }
+            }
+        }
+        info!("== Loaded {} synonym groups", groups.len());
+    }
+
+    /// Expand the `terms` of a query in `language` with their synonyms
+    ///
+    /// `language` is the language of the pipeline which analysed the
+    /// query, see [`Languages::resolve`].
+    /// Synonyms already among the terms of the query are not added
+    /// again.
+    ///
+    /// [`Languages::resolve`]: ../language/struct.Languages.html#method.resolve
+    #[must_use]
+    pub fn expand(&self, language: &str, terms: Vec<String>) -> ExpandedQuery {
+        let mut words: Vec<(String, i32)> = terms
+            .iter()
+            .map(|term| (term.clone(), QUERY_WEIGHT))
+            .collect();
+        if let Some(synonyms) = self.expansions.get(language) {
+            for expansions in terms.iter().filter_map(|t| synonyms.get(t)) {
+                for synonym in expansions {
+                    if !words.iter().any(|(word, _)| word == synonym) {
+                        words.push((synonym.clone(), self.weight));
+                    }
+                }
+            }
+        }
+        ExpandedQuery { terms, words }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzer::Analyzer;
+    use crate::language::Pipeline;
+
+    fn group(language: &str, words: &[&str]) -> SynonymGroup {
+        SynonymGroup {
+            id: 1,
+            language: language.to_string(),
+            words: words.iter().map(ToString::to_string).collect(),
+            expansions: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn groups_are_found_under_the_language_of_their_pipeline() {
+        let languages =
+            Languages::new("fr", Pipeline::new(Analyzer::default(), None))
+                .unwrap();
+        let mut synonyms = Synonyms::new(50);
+        synonyms.load(
+            &[
+                group("fr", &["voiture", "auto"]),
+                group("de", &["wagen", "pkw"]),
+            ],
+            &languages,
+        );
+        let language = languages.resolve(Some("fr-FR"));
+        let query = synonyms.expand(language, vec!["voiture".to_string()]);
+        assert_eq!(
+            query.words,
+            [
+                ("voiture".to_string(), QUERY_WEIGHT),
+                ("auto".to_string(), 50)
+            ]
+        );
+        let language = languages.resolve(Some("de"));
+        let query = synonyms.expand(language, vec!["wagen".to_string()]);
+        assert_eq!(query.words.len(), 2);
+    }
+}
@@ -0,0 +1,422 @@
+use std::collections::VecDeque;
+use std::str::SplitWhitespace;
+
+use unicode_normalization::char::is_combining_mark;
+
+/// Elided articles and pronouns of French, stripped from the words
+/// they are attached to
+const FRENCH_ELISIONS: [&str; 13] = [
+    "l", "d", "j", "m", "n", "s", "t", "c", "qu", "jusqu", "lorsqu", "puisqu",
+    "quoiqu",
+];
+
+/// Characters which may appear inside a token besides letters and
+/// digits
+const INNER_CHARACTERS: [char; 5] = ['-', '\'', '’', '+', '#'];
+
+/// Punctuation which may end a URL or an email address without being
+/// part of it
+const TRAILING_PUNCTUATION: &[char] = &[
+    '.', ',', ';', ':', '!', '?', ')', ']', '}', '"', '\'', '’', '»',
+];
+
+/// Kind of a token, which determines how it is analysed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    /// Word made of letters only, such as `école`
+    Word,
+    /// Hyphenated compound of words, such as `e-mail`
+    Compound,
+    /// Token mixing letters, digits, or symbols, such as `covid-19`,
+    /// `3d`, or `c++`
+    Alphanumeric,
+    /// Number, such as a year
+    Number,
+    /// URL, such as `https://example.com/`
+    Url,
+    /// Email address
+    Email,
+}
+
+impl TokenKind {
+    /// Whether tokens of this kind are made of words of a language,
+    /// and can be lemmatized or stemmed
+    #[must_use]
+    pub fn is_linguistic(self) -> bool {
+        matches!(self, Self::Word | Self::Compound)
+    }
+}
+
+/// Token found in a text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Token<'a> {
+    pub text: &'a str,
+    pub kind: TokenKind,
+    /// Offset in bytes of the token in the text
+    pub offset: usize,
+    /// Position of the token in the text, counted in words
+    ///
+    /// Words shorter than the minimum length are left out but keep
+    /// their position, so that positions of tokens are only adjacent
+    /// if their words are. The parts of a hyphenated compound have
+    /// their own positions, and the compound has the position of its
+    /// first part.
+    pub position: usize,
+}
+
+/// Split texts into tokens
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tokenizer {
+    /// Minimum length in characters of words, shorter words are
+    /// ignored. Tokens containing digits or symbols are always kept.
+    pub min_length: usize,
+    /// Elided words stripped from the beginning of words, such as
+    /// `l` in `l’école`
+    pub elisions: &'static [&'static str],
+}
+
+impl Default for Tokenizer {
+    fn default() -> Self {
+        Self {
+            min_length: 3,
+            elisions: &[],
+        }
+    }
+}
+
+/// Whether `chunk` looks like a URL
+fn is_url(chunk: &str) -> bool {
+    let lowercase = chunk.to_lowercase();
+    (chunk.contains("://") || lowercase.starts_with("www.")) && chunk.len() > 4
+}
+
+/// Whether `chunk` looks like an email address
+fn is_email(chunk: &str) -> bool {
+    match chunk.split_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty()
+                && domain.contains('.')
+                && !domain.starts_with('.')
+                && !domain.ends_with('.')
+                && !domain.contains('@')
+        }
+        None => false,
+    }
+}
+
+/// Whether `c` is part of a letter, including combining marks of
+/// decomposed letters
+fn is_letter(c: char) -> bool {
+    c.is_alphabetic() || is_combining_mark(c)
+}
+
+/// Whether `c` is part of a letter or a digit
+fn is_alphanumeric(c: char) -> bool {
+    c.is_alphanumeric() || is_combining_mark(c)
+}
+
+/// Kind of a token made of a single word, without hyphens
+fn word_kind(word: &str) -> TokenKind {
+    if word.chars().all(is_letter) {
+        TokenKind::Word
+    } else if word.chars().all(|c| c.is_ascii_digit()) {
+        TokenKind::Number
+    } else {
+        TokenKind::Alphanumeric
+    }
+}
+
+impl Tokenizer {
+    /// Tokenizer suited to `language`, given as an ISO 639-1 code
+    ///
+    /// Elisions are stripped in French only.
+    #[must_use]
+    pub fn for_language(language: &str, min_length: usize) -> Self {
+        Self {
+            min_length,
+            elisions: if language == "fr" {
+                &FRENCH_ELISIONS
+            } else {
+                &[]
+            },
+        }
+    }
+
+    /// Split `text` into tokens, in the order they appear in
+    ///
+    /// URLs and email addresses are kept whole. Other tokens are
+    /// words of letters and digits which may contain hyphens or end
+    /// with `++` or `#`. Elided words are stripped, other apostrophes
+    /// split words. Words shorter than the minimum length are left
+    /// out.
+    ///
+    /// Tokens are read lazily, and their offsets refer to `text`
+    /// itself, which is therefore not normalized beforehand.
+    #[must_use]
+    pub fn tokenize<'a>(&self, text: &'a str) -> Tokens<'a> {
+        Tokens {
+            tokenizer: *self,
+            text,
+            chunks: text.split_whitespace(),
+            pending: VecDeque::new(),
+            position: 0,
+        }
+    }
+
+    /// Strip the elided word at the beginning of `word`, if any
+    fn strip_elision<'a>(&self, word: &'a str) -> &'a str {
+        word.find(['\'', '’'])
+            .filter(|index| {
+                let elided = word[..*index].to_lowercase();
+                self.elisions.contains(&elided.as_str())
+            })
+            .map_or(word, |index| {
+                let apostrophe = word[index..].chars().next().unwrap_or('\'');
+                &word[index + apostrophe.len_utf8()..]
+            })
+    }
+
+    /// Whether a token is long enough to be kept
+    fn is_long_enough(&self, token: &Token) -> bool {
+        match token.kind {
+            TokenKind::Word | TokenKind::Compound => {
+                token.text.chars().count() >= self.min_length
+            }
+            _ => token.text.chars().count() >= 2,
+        }
+    }
+}
+
+/// Iterator over the tokens of a text, see [`Tokenizer::tokenize`]
+///
+/// [`Tokenizer::tokenize`]: ./struct.Tokenizer.html#method.tokenize
+pub struct Tokens<'a> {
+    tokenizer: Tokenizer,
+    text: &'a str,
+    chunks: SplitWhitespace<'a>,
+    /// Tokens of the current chunk not returned yet
+    pending: VecDeque<Token<'a>>,
+    /// Position of the next word
+    position: usize,
+}
+
+impl<'a> Tokens<'a> {
+    /// Amount of positions taken by the tokens read so far
+    #[must_use]
+    pub fn positions(&self) -> usize {
+        self.position
+    }
+
+    /// Queue a token of the current chunk, if it is long enough
+    fn push(&mut self, text: &'a str, kind: TokenKind, position: usize) {
+        let token = Token {
+            text,
+            kind,
+            offset: text.as_ptr() as usize - self.text.as_ptr() as usize,
+            position,
+        };
+        if self.tokenizer.is_long_enough(&token) {
+            self.pending.push_back(token);
+        }
+    }
+
+    /// Queue the next word, taking the next position
+    fn push_word(&mut self, text: &'a str, kind: TokenKind) {
+        self.push(text, kind, self.position);
+        self.position += 1;
+    }
+
+    /// Split a chunk of text without whitespace into tokens
+    fn split_chunk(&mut self, chunk: &'a str) {
+        let trimmed = chunk
+            .trim_start_matches(|c: char| !is_alphanumeric(c))
+            .trim_end_matches(TRAILING_PUNCTUATION);
+        if is_url(trimmed) {
+            self.push_word(trimmed, TokenKind::Url);
+        } else if is_email(trimmed) {
+            self.push_word(trimmed, TokenKind::Email);
+        } else {
+            let words = chunk.split(|c: char| {
+                !is_alphanumeric(c) && !INNER_CHARACTERS.contains(&c)
+            });
+            for word in words {
+                self.split_word(word);
+            }
+        }
+    }
+
+    /// Split a word into tokens
+    ///
+    /// Symbols other than a trailing `++` or `#`, such as in `c++` and
+    /// `c#`, split the word. Hyphenated compounds are kept whole, and
+    /// each of their parts is a token as well.
+    fn split_word(&mut self, word: &'a str) {
+        let word = self.tokenizer.strip_elision(word);
+        let word = word.trim_start_matches(|c: char| !is_alphanumeric(c));
+        let stripped = word.trim_end_matches(|c: char| !is_alphanumeric(c));
+        let suffix = &word[stripped.len()..];
+        let word = if suffix.starts_with("++") {
+            &word[..stripped.len() + 2]
+        } else if suffix.starts_with('#') {
+            &word[..=stripped.len()]
+        } else {
+            stripped
+        };
+        if word.is_empty() {
+            return;
+        }
+        let symbol = word[..stripped.len()]
+            .find(|c: char| !is_alphanumeric(c) && c != '-');
+        if let Some(index) = symbol {
+            let length = word[index..].chars().next().map_or(1, char::len_utf8);
+            self.split_word(&word[..index]);
+            self.split_word(&word[index + length..]);
+            return;
+        }
+        if word.contains('-') {
+            let kind = if word.chars().all(|c| is_letter(c) || c == '-') {
+                TokenKind::Compound
+            } else {
+                TokenKind::Alphanumeric
+            };
+            self.push(word, kind, self.position);
+            for part in word.split('-').filter(|part| !part.is_empty()) {
+                self.push_word(part, word_kind(part));
+            }
+        } else {
+            self.push_word(word, word_kind(word));
+        }
+    }
+}
+
+impl<'a> Iterator for Tokens<'a> {
+    type Item = Token<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(token) = self.pending.pop_front() {
+                return Some(token);
+            }
+            let chunk = self.chunks.next()?;
+            self.split_chunk(chunk);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts<'a>(tokenizer: &Tokenizer, text: &'a str) -> Vec<&'a str> {
+        tokenizer.tokenize(text).map(|token| token.text).collect()
+    }
+
+    #[test]
+    fn french_elisions_are_stripped() {
+        let tokenizer = Tokenizer::for_language("fr", 3);
+        let text = "L’école d'été qu’elle aujourd'hui";
+        assert_eq!(
+            texts(&tokenizer, text),
+            ["école", "été", "elle", "aujourd", "hui"]
+        );
+    }
+
+    #[test]
+    fn elisions_are_only_stripped_in_french() {
+        let french = Tokenizer::for_language("fr", 1);
+        let english = Tokenizer::for_language("en", 1);
+        assert_eq!(texts(&french, "l’école"), ["école"]);
+        assert_eq!(texts(&english, "l’école"), ["l", "école"]);
+        assert_eq!(texts(&english, "don't"), ["don", "t"]);
+    }
+
+    #[test]
+    fn compounds_are_kept_with_their_parts() {
+        let tokens = Tokenizer::default()
+            .tokenize("les arcs-en-ciel")
+            .collect::<Vec<_>>();
+        let summary = tokens
+            .iter()
+            .map(|token| (token.text, token.kind, token.position))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            [
+                ("les", TokenKind::Word, 0),
+                ("arcs-en-ciel", TokenKind::Compound, 1),
+                ("arcs", TokenKind::Word, 1),
+                ("ciel", TokenKind::Word, 3),
+            ]
+        );
+    }
+
+    #[test]
+    fn alphanumeric_tokens_are_kept() {
+        let tokens = Tokenizer::default()
+            .tokenize("covid-19 en 2020, c++ et c# 3d")
+            .map(|token| (token.text, token.kind))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            tokens,
+            [
+                ("covid-19", TokenKind::Alphanumeric),
+                ("covid", TokenKind::Word),
+                ("19", TokenKind::Number),
+                ("2020", TokenKind::Number),
+                ("c++", TokenKind::Alphanumeric),
+                ("c#", TokenKind::Alphanumeric),
+                ("3d", TokenKind::Alphanumeric),
+            ]
+        );
+    }
+
+    #[test]
+    fn urls_and_emails_are_kept_whole() {
+        let text = "Voir (https://example.com/a-b?c=d). ou contact@example.fr.";
+        let tokens = Tokenizer::default()
+            .tokenize(text)
+            .filter(|token| !token.kind.is_linguistic())
+            .map(|token| (token.text, token.kind))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            tokens,
+            [
+                ("https://example.com/a-b?c=d", TokenKind::Url),
+                ("contact@example.fr", TokenKind::Email),
+            ]
+        );
+    }
+
+    #[test]
+    fn short_words_keep_their_position() {
+        let tokens = Tokenizer::default()
+            .tokenize("le chat a mangé")
+            .map(|token| (token.text, token.position))
+            .collect::<Vec<_>>();
+        assert_eq!(tokens, [("chat", 1), ("mangé", 3)]);
+    }
+
+    #[test]
+    fn offsets_refer_to_the_text() {
+        let text = "  «Été»  brûlant";
+        for token in Tokenizer::default().tokenize(text) {
+            assert_eq!(&text[token.offset..][..token.text.len()], token.text);
+        }
+    }
+
+    #[test]
+    fn positions_count_all_words() {
+        let mut tokens = Tokenizer::default().tokenize("un deux trois");
+        assert_eq!(tokens.by_ref().count(), 2);
+        assert_eq!(tokens.positions(), 3);
+    }
+
+    #[test]
+    fn decomposed_letters_are_words() {
+        let tokens = Tokenizer::default()
+            .tokenize("e\u{301}te\u{301}")
+            .map(|token| token.kind)
+            .collect::<Vec<_>>();
+        assert_eq!(tokens, [TokenKind::Word]);
+    }
+}